@@ -1,4 +1,6 @@
 use now_lambda::http::StatusCode;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 pub struct Response {
   pub code: now_lambda::http::StatusCode,
@@ -14,6 +16,9 @@ pub enum RequestError {
   #[error("Not found")]
   NotFoundError,
 
+  #[error("Invalid reference '{0}'")]
+  ReferenceError(String),
+
   #[error("Search Error: {0}")]
   SearchError(#[from] study_map_index::Error),
 
@@ -29,12 +34,63 @@ impl RequestError {
     match self {
       RequestError::QueryStringError(_) => StatusCode::BAD_REQUEST,
       RequestError::NotFoundError => StatusCode::NOT_FOUND,
+      RequestError::ReferenceError(_) => StatusCode::BAD_REQUEST,
       RequestError::SearchError(study_map_index::Error::QueryParseError) => StatusCode::BAD_REQUEST,
+      RequestError::SearchError(study_map_index::Error::NotFound) => StatusCode::NOT_FOUND,
       _ => StatusCode::INTERNAL_SERVER_ERROR,
     }
   }
 }
 
+/// The path of a file in the data directory, from a name that may come from a request.
+pub fn data_path(file_name: &str) -> Result<std::path::PathBuf, RequestError> {
+  let data_dir_path = std::path::Path::new("./data").canonicalize()?;
+  let path = data_dir_path
+    .join(file_name)
+    .canonicalize()
+    .map_err(|_e| RequestError::NotFoundError)?;
+
+  if !path.ancestors().any(|parent| parent == data_dir_path) {
+    // The request is attempting some path trickery.
+    return Err(RequestError::NotFoundError);
+  }
+
+  Ok(path)
+}
+
+/// Data that takes a while to load for a text, such as its vectors or postings, kept for
+/// later requests. Only the most recently used texts are kept, since the data for every
+/// text won't fit in memory.
+pub struct TextCache<T> {
+  capacity: usize,
+  /// The loaded texts, most recently used first.
+  texts: RefCell<Vec<(String, Rc<T>)>>,
+}
+
+impl<T> TextCache<T> {
+  pub fn new(capacity: usize) -> TextCache<T> {
+    TextCache {
+      capacity: capacity.max(1),
+      texts: RefCell::new(Vec::new()),
+    }
+  }
+
+  /// The data for a text, calling `load` when it isn't in the cache.
+  pub fn get<E>(&self, book_id: &str, load: impl FnOnce() -> Result<T, E>) -> Result<Rc<T>, E> {
+    let mut texts = self.texts.borrow_mut();
+    if let Some(i) = texts.iter().position(|(id, _)| id == book_id) {
+      let entry = texts.remove(i);
+      texts.insert(0, entry);
+    } else {
+      let data = Rc::new(load()?);
+      texts.truncate(self.capacity - 1);
+      texts.insert(0, (String::from(book_id), data));
+    }
+
+    Ok(texts[0].1.clone())
+  }
+}
+
 pub fn respond(
   r: Result<Response, RequestError>,
 ) -> Result<now_lambda::Response<String>, now_lambda::http::Error> {
@@ -53,3 +109,23 @@ pub fn respond(
     .header("Cache-Control", "max-age=300, s-maxage=31536000")
     .body(res.data)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn text_cache_drops_least_recently_used() {
+    let cache = TextCache::new(2);
+    let load = |value: usize| move || Ok::<_, ()>(value);
+    assert_eq!(*cache.get("a", load(1)).unwrap(), 1);
+    assert_eq!(*cache.get("b", load(2)).unwrap(), 2);
+    // "a" is still cached, and is now the most recently used.
+    assert_eq!(*cache.get("a", load(10)).unwrap(), 1);
+    assert_eq!(*cache.get("c", load(3)).unwrap(), 3);
+    // "b" was dropped to make room.
+    assert_eq!(*cache.get("b", load(20)).unwrap(), 20);
+    assert_eq!(*cache.get("c", load(30)).unwrap(), 3);
+    assert_eq!(*cache.get("a", load(100)).unwrap(), 100);
+  }
+}
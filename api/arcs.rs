@@ -0,0 +1,102 @@
+use lib::{respond, RequestError, Response};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use study_map_index::{
+  books::{range_bounds, BookMap},
+  crossrefs::*,
+  index::Catalog,
+};
+
+#[derive(Deserialize)]
+struct Qs {
+  /// Only count references from this range of verses. Without l0, every reference counts.
+  l0: Option<usize>,
+  l1: Option<usize>,
+  l2: Option<usize>,
+  end_l1: Option<usize>,
+  end_l2: Option<usize>,
+  /// Only count references from this dataset.
+  source: Option<String>,
+  min_votes: Option<i64>,
+  /// Give the locations in the query and the arcs in this translation's coordinates rather
+  /// than canonical ones. Arcs to books that it doesn't have are left out.
+  book_id: Option<String>,
+}
+
+struct Context {
+  crossrefs: CrossRefs,
+  books: HashMap<String, BookMap>,
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let books = match qs.book_id.as_deref() {
+    Some(book_id) => Some(ctx.books.get(book_id).ok_or(RequestError::NotFoundError)?),
+    None => None,
+  };
+  let l0 = match (qs.l0, books) {
+    (Some(l0), Some(books)) => match books.canonical(l0) {
+      Some(l0) => Some(l0),
+      // The book isn't part of the canon, so no references are stored for it.
+      None => return json_response(&[]),
+    },
+    (l0, _) => l0,
+  };
+
+  let (start, end) = match l0 {
+    Some(l0) => range_bounds(l0, qs.l1, qs.l2, qs.end_l1, qs.end_l2),
+    None => ((0, 0, 0), (usize::MAX, usize::MAX, usize::MAX)),
+  };
+
+  let sources = match qs.source.as_deref() {
+    Some(source) => vec![source],
+    None => ctx.crossrefs.sources().collect(),
+  };
+
+  let arcs = ctx
+    .crossrefs
+    .chapter_arcs(&sources, start, end, qs.min_votes)
+    .into_iter()
+    .filter_map(|arc| match books {
+      Some(books) => Some(ChapterArc {
+        from: books.to_text(arc.from)?,
+        to: books.to_text(arc.to)?,
+        count: arc.count,
+      }),
+      None => Some(arc),
+    })
+    .collect::<Vec<_>>();
+
+  json_response(&arcs)
+}
+
+fn json_response(arcs: &[ChapterArc]) -> Result<Response, RequestError> {
+  let output = serde_json::to_string(arcs).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let data_dir = Path::new("./data");
+  let ctx = Context {
+    crossrefs: CrossRefs::load(data_dir)?,
+    books: Catalog::load(data_dir)?
+      .texts
+      .into_iter()
+      .map(|item| Ok((item.id.clone(), BookMap::load(data_dir, &item.id)?)))
+      .collect::<anyhow::Result<_>>()?,
+  };
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Result};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use lib::{respond, RequestError, Response, TextCache};
+
+use study_map_index::{
+  books::{parse_osis_range, BookMap}, collocations::*, corpus::Corpus, index::*, postings::TextPostings,
+};
+
+#[derive(Deserialize)]
+struct Qs {
+  book_id: String,
+  word: String,
+  /// An OSIS reference such as "John", "Matt-Luke", or "Gen.1-Gen.11". Defaults to the
+  /// whole text.
+  range: Option<String>,
+  /// Count words within this many words of `word`, instead of in the same verse.
+  window: Option<u32>,
+  measure: Option<CollocationMeasure>,
+  limit: Option<usize>,
+  min_freq: Option<usize>,
+}
+
+/// A text's postings and surface forms take a while to read, so the last few texts are
+/// kept for later requests.
+struct Context {
+  index: tantivy::Index,
+  texts: TextCache<(TextPostings, HashMap<String, String>)>,
+  books: HashMap<String, BookMap>,
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let books = ctx.books.get(&qs.book_id).ok_or(RequestError::NotFoundError)?;
+
+  let mut options = CollocationOptions {
+    window: qs.window,
+    ..CollocationOptions::default()
+  };
+  if let Some(measure) = qs.measure {
+    options.measure = measure;
+  }
+  if let Some(limit) = qs.limit {
+    options.limit = limit;
+  }
+  if let Some(min_freq) = qs.min_freq {
+    options.min_freq = min_freq;
+  }
+
+  let text = ctx.texts.get(&qs.book_id, || {
+    let postings = TextPostings::load(&ctx.index, &qs.book_id)?;
+    let surface_forms = Corpus::load(&ctx.index, &qs.book_id, None)?.surface_forms();
+    Ok::<_, RequestError>((postings, surface_forms))
+  })?;
+  let (postings, surface_forms) = &*text;
+
+  // The range is a canonical OSIS reference, so find the verses that it covers in this text.
+  let range = qs
+    .range
+    .as_deref()
+    .map(|r| {
+      parse_osis_range(r)
+        .and_then(|bounds| books.range(bounds))
+        .filter(|(start, end)| !postings.verse_range(*start, *end).is_empty())
+        .ok_or_else(|| RequestError::ReferenceError(String::from(r)))
+    })
+    .transpose()?;
+  let node = postings
+    .analyze(&ctx.index, &qs.word)?
+    .first()
+    .and_then(|term| postings.term(term))
+    .ok_or(RequestError::NotFoundError)?;
+  let results = collocations(postings, surface_forms, node, range, &options);
+  let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let index_dir = Path::new("./data");
+  let ctx = Context {
+    index: open_readonly_index(index_dir).map_err(|e| anyhow!("Opening index: {}", e))?,
+    texts: TextCache::new(2),
+    books: Catalog::load(index_dir)?
+      .texts
+      .into_iter()
+      .map(|item| Ok((item.id.clone(), BookMap::load(index_dir, &item.id)?)))
+      .collect::<Result<_>>()?,
+  };
+
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -0,0 +1,167 @@
+use anyhow::{anyhow, Result};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use lib::{respond, RequestError, Response};
+
+use study_map_index::{books::{range_bounds, BookMap}, crossrefs::*, index::*, search::*};
+
+#[derive(Deserialize)]
+struct Qs {
+  l0: usize,
+  l1: Option<usize>,
+  l2: Option<usize>,
+  /// The end of a range of verses in the same book.
+  end_l1: Option<usize>,
+  end_l2: Option<usize>,
+  /// Only return references from this dataset.
+  source: Option<String>,
+  /// Include the text of each referenced passage from this translation. The locations in
+  /// the query and the results are then in the translation's coordinates rather than
+  /// canonical ones, and references to books that it doesn't have are left out.
+  book_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CrossRefResult<'a> {
+  source: &'a str,
+  #[serde(flatten)]
+  crossref: CrossRef,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  text: Option<Vec<TextResult>>,
+}
+
+struct Context<'a> {
+  searcher: Searcher<'a>,
+  crossrefs: CrossRefs,
+  books: HashMap<String, BookMap>,
+}
+
+/// A cross reference in the coordinates of a translation, if it has the referenced books.
+fn text_crossref(books: &BookMap, crossref: &CrossRef) -> Option<CrossRef> {
+  Some(CrossRef {
+    from: books.to_text(crossref.from)?,
+    to: books.to_text(crossref.to)?,
+    to_end: match crossref.to_end {
+      Some(to_end) => Some(books.to_text(to_end)?),
+      None => None,
+    },
+    votes: crossref.votes,
+  })
+}
+
+/// The verses of a referenced passage, in the translation's coordinates. Ranges are read a
+/// chapter at a time.
+fn ref_text(
+  searcher: &Searcher,
+  book_id: &str,
+  crossref: &CrossRef,
+) -> Result<Vec<TextResult>, RequestError> {
+  let to = crossref.to;
+  let to_end = match crossref.to_end {
+    Some(to_end) if to_end.l0 == to.l0 => to_end,
+    _ => {
+      return Ok(searcher.get_text(book_id, to.l0, Some(to.l1), to.l2, &TextOptions::default())?)
+    }
+  };
+
+  let mut verses = Vec::new();
+  for l1 in to.l1..=to_end.l1 {
+    let chapter = searcher.get_text(book_id, to.l0, Some(l1), None, &TextOptions::default())?;
+    verses.extend(chapter.into_iter().filter(|verse| {
+      let location = (to.l0, verse.l1.unwrap_or(0), verse.l2.unwrap_or(0));
+      location >= to.first_verse() && location <= to_end.last_verse()
+    }));
+  }
+  verses.sort_by_key(|verse| (verse.l1, verse.l2));
+
+  Ok(verses)
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let books = match qs.book_id.as_deref() {
+    Some(book_id) => Some(ctx.books.get(book_id).ok_or(RequestError::NotFoundError)?),
+    None => None,
+  };
+  let l0 = match books {
+    Some(books) => match books.canonical(qs.l0) {
+      Some(l0) => l0,
+      // The book isn't part of the canon, so no references are stored for it.
+      None => return json_response(&Vec::<CrossRefResult>::new()),
+    },
+    None => qs.l0,
+  };
+  let (start, end) = range_bounds(l0, qs.l1, qs.l2, qs.end_l1, qs.end_l2);
+
+  let sources = match qs.source.as_deref() {
+    Some(source) => vec![source],
+    None => ctx.crossrefs.sources().collect(),
+  };
+
+  let results = sources
+    .into_iter()
+    .flat_map(|source| {
+      ctx
+        .crossrefs
+        .range(source, start, end)
+        .iter()
+        .map(move |crossref| (source, crossref))
+    })
+    .filter_map(|(source, crossref)| match (qs.book_id.as_deref(), books) {
+      (Some(book_id), Some(books)) => {
+        let crossref = text_crossref(books, crossref)?;
+        Some(
+          ref_text(&ctx.searcher, book_id, &crossref).map(|text| CrossRefResult {
+            source,
+            crossref,
+            text: Some(text),
+          }),
+        )
+      }
+      _ => Some(Ok(CrossRefResult {
+        source,
+        crossref: crossref.clone(),
+        text: None,
+      })),
+    })
+    .collect::<Result<Vec<_>, RequestError>>()?;
+
+  json_response(&results)
+}
+
+fn json_response(results: &[CrossRefResult]) -> Result<Response, RequestError> {
+  let output = serde_json::to_string(results).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let index_dir = Path::new("./data");
+  let index = Rc::new(open_readonly_index(index_dir).map_err(|e| anyhow!("Opening index: {}", e))?);
+  let searcher = Searcher::new(&index)?;
+
+  let ctx = Context {
+    searcher,
+    crossrefs: CrossRefs::load(index_dir)?,
+    books: Catalog::load(index_dir)?
+      .texts
+      .into_iter()
+      .map(|item| Ok((item.id.clone(), BookMap::load(index_dir, &item.id)?)))
+      .collect::<Result<_>>()?,
+  };
+
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -1,4 +1,4 @@
-use lib::{respond, RequestError, Response};
+use lib::{data_path, respond, RequestError, Response};
 use now_lambda::{http::StatusCode, lambda, Request};
 use serde::Deserialize;
 use std::io::Read;
@@ -6,22 +6,34 @@ use std::io::Read;
 #[derive(Deserialize)]
 struct Qs {
   book_id: String,
+  /// Which of the text's data files to return. Defaults to its statistics.
+  data: Option<DataFile>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum DataFile {
+  Stats,
+  Topics,
+  Vocabulary,
+}
+
+impl DataFile {
+  fn prefix(&self) -> &'static str {
+    match self {
+      DataFile::Stats => "stats",
+      DataFile::Topics => "topics",
+      DataFile::Vocabulary => "vocabulary",
+    }
+  }
 }
 
 fn handler(req: Request) -> Result<Response, RequestError> {
   let q = req.uri().query().unwrap_or("");
   let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
 
-  let data_dir_path = std::path::Path::new("./data").canonicalize()?;
-  let path = data_dir_path
-    .join(format!("stats-{}.json", qs.book_id))
-    .canonicalize()?;
-
-  if !path.ancestors().any(|parent| parent == data_dir_path) {
-    // The request is attempting some path trickery.
-    return Err(RequestError::NotFoundError);
-  }
-
+  let data = qs.data.unwrap_or(DataFile::Stats);
+  let path = data_path(&format!("{}-{}.json", data.prefix(), qs.book_id))?;
   let mut f = std::fs::File::open(path).map_err(|_e| RequestError::NotFoundError)?;
   let mut catalog_data = String::new();
   f.read_to_string(&mut catalog_data)?;
@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use lib::{respond, RequestError, Response, TextCache};
+
+use study_map_index::{
+  books::{parse_osis_range, BookMap}, corpus::Corpus, index::*, keyness::*, postings::TextPostings,
+};
+
+#[derive(Deserialize)]
+struct Qs {
+  book_id: String,
+  /// An OSIS reference such as "John", "Matt-Luke", or "Gen.1-Gen.11".
+  range: String,
+  /// The range to compare against. Defaults to the rest of the text.
+  reference: Option<String>,
+  measure: Option<KeynessMeasure>,
+  limit: Option<usize>,
+  min_freq: Option<usize>,
+}
+
+/// A text's postings and surface forms take a while to read, so the last few texts are
+/// kept for later requests.
+struct Context {
+  index: tantivy::Index,
+  texts: TextCache<(TextPostings, HashMap<String, String>)>,
+  books: HashMap<String, BookMap>,
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let books = ctx.books.get(&qs.book_id).ok_or(RequestError::NotFoundError)?;
+
+  let mut options = KeynessOptions::default();
+  if let Some(measure) = qs.measure {
+    options.measure = measure;
+  }
+  if let Some(limit) = qs.limit {
+    options.limit = limit;
+  }
+  if let Some(min_freq) = qs.min_freq {
+    options.min_freq = min_freq;
+  }
+
+  let text = ctx.texts.get(&qs.book_id, || {
+    let postings = TextPostings::load(&ctx.index, &qs.book_id)?;
+    let surface_forms = Corpus::load(&ctx.index, &qs.book_id, None)?.surface_forms();
+    Ok::<_, RequestError>((postings, surface_forms))
+  })?;
+  let (postings, surface_forms) = &*text;
+
+  // Ranges are canonical OSIS references, so find the verses that they cover in this text.
+  let parse_range = |r: &str| {
+    parse_osis_range(r)
+      .and_then(|bounds| books.range(bounds))
+      .filter(|(start, end)| !postings.verse_range(*start, *end).is_empty())
+      .ok_or_else(|| RequestError::ReferenceError(String::from(r)))
+  };
+  let target = parse_range(&qs.range)?;
+  let reference = qs.reference.as_deref().map(parse_range).transpose()?;
+  let results = keyness(postings, surface_forms, target, reference, &options);
+  let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let index_dir = Path::new("./data");
+  let ctx = Context {
+    index: open_readonly_index(index_dir).map_err(|e| anyhow!("Opening index: {}", e))?,
+    texts: TextCache::new(2),
+    books: Catalog::load(index_dir)?
+      .texts
+      .into_iter()
+      .map(|item| Ok((item.id.clone(), BookMap::load(index_dir, &item.id)?)))
+      .collect::<Result<_>>()?,
+  };
+
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::rc::Rc;
+
+use lib::{respond, RequestError, Response};
+
+use study_map_index::{index::*, lexicon::*, normalize_strong, search::*};
+
+#[derive(Deserialize)]
+struct Qs {
+  id: String,
+  book_ids: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct LexiconResult<'a> {
+  entry: &'a LexiconEntry,
+  /// The search query for the word, so that the map can be driven from the entry.
+  query: String,
+  occurrences: Vec<BookCount>,
+}
+
+struct Context<'a> {
+  searcher: Searcher<'a>,
+  lexicon: Lexicon,
+  book_ids: Vec<String>,
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let id = normalize_strong(&qs.id).unwrap_or(qs.id);
+  let entry = ctx.lexicon.get(&id).ok_or(RequestError::NotFoundError)?;
+  let query = entry.query();
+  let book_ids = qs.book_ids.unwrap_or_else(|| ctx.book_ids.clone());
+  let occurrences = ctx.searcher.count(&query, &book_ids)?;
+
+  let result = LexiconResult {
+    entry,
+    query,
+    occurrences,
+  };
+  let output = serde_json::to_string(&result).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let index_dir = Path::new("./data");
+  let index = Rc::new(open_readonly_index(index_dir).map_err(|e| anyhow!("Opening index: {}", e))?);
+  let searcher = Searcher::new(&index)?;
+  let book_ids = Catalog::load(index_dir)?
+    .texts
+    .into_iter()
+    .map(|item| item.id)
+    .collect();
+
+  let ctx = Context {
+    searcher,
+    lexicon: Lexicon::load(index_dir)?,
+    book_ids,
+  };
+
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -0,0 +1,38 @@
+use lib::{data_path, respond, RequestError, Response};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::Deserialize;
+
+use study_map_index::{books::range_bounds, parallels::*};
+
+#[derive(Deserialize)]
+struct Qs {
+  book_id: String,
+  l0: usize,
+  l1: Option<usize>,
+  l2: Option<usize>,
+  end_l1: Option<usize>,
+  end_l2: Option<usize>,
+}
+
+fn handler(req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let path = data_path(&Parallels::file_name(&qs.book_id))?;
+  let parallels = Parallels::load(&path)?;
+
+  let (start, end) = range_bounds(qs.l0, qs.l1, qs.l2, qs.end_l1, qs.end_l2);
+  let results = parallels.range(start, end);
+  let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  Ok(lambda!(|req| respond(handler(req))))
+}
@@ -0,0 +1,57 @@
+use lib::{data_path, respond, RequestError, Response, TextCache};
+use now_lambda::{http::StatusCode, lambda, Request};
+use serde::Deserialize;
+
+use study_map_index::{books::Location, vectors::*};
+
+#[derive(Deserialize)]
+struct Qs {
+  book_id: String,
+  l0: usize,
+  l1: usize,
+  /// Find related verses when given, or related chapters otherwise.
+  l2: Option<usize>,
+  limit: Option<usize>,
+}
+
+/// Vector sets are large, so only a few are kept for later requests.
+struct Context {
+  vectors: TextCache<VectorSet>,
+}
+
+fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
+  let q = req.uri().query().unwrap_or("");
+  let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
+
+  let vectors = ctx.vectors.get(&qs.book_id, || {
+    let path = data_path(&VectorSet::file_name(&qs.book_id))?;
+    Ok::<_, RequestError>(VectorSet::load(&path)?)
+  })?;
+
+  let location = Location {
+    l0: qs.l0,
+    l1: qs.l1,
+    l2: qs.l2,
+  };
+  let results = vectors
+    .nearest(location, qs.limit.unwrap_or(20))
+    .ok_or(RequestError::NotFoundError)?;
+  let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
+
+  Ok(Response {
+    code: StatusCode::OK,
+    content_type: "application/json",
+    data: output,
+  })
+}
+
+// Start the runtime with the handler
+fn main() -> anyhow::Result<()> {
+  let ctx = Context {
+    vectors: TextCache::new(2),
+  };
+
+  let handler_wrapper = |req: Request| respond(handler(&ctx, req));
+
+  Ok(lambda!(handler_wrapper))
+}
@@ -6,12 +6,19 @@ use std::rc::Rc;
 
 use lib::{respond, RequestError, Response};
 
-use study_map_index::{index::*, search::*};
+use study_map_index::{index::*, search::*, synonyms::Synonyms};
 
 #[derive(Deserialize)]
 struct Qs {
   book_ids: Option<Vec<String>>,
   query: String,
+  mode: Option<SearchMode>,
+  expand_synonyms: Option<bool>,
+  window: Option<Window>,
+  granularity: Option<Granularity>,
+  headings: Option<bool>,
+  notes: Option<Filter>,
+  red_letter: Option<Filter>,
 }
 
 struct Context<'a> {
@@ -24,9 +31,30 @@ fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
 
   let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
 
+  let mut options = SearchOptions::default();
+  if let Some(mode) = qs.mode {
+    options.mode = mode;
+  }
+  if let Some(expand_synonyms) = qs.expand_synonyms {
+    options.expand_synonyms = expand_synonyms;
+  }
+  options.window = qs.window;
+  if let Some(granularity) = qs.granularity {
+    options.granularity = granularity;
+  }
+  if let Some(headings) = qs.headings {
+    options.headings = headings;
+  }
+  if let Some(notes) = qs.notes {
+    options.notes = notes;
+  }
+  if let Some(red_letter) = qs.red_letter {
+    options.red_letter = red_letter;
+  }
+
   let results = ctx
     .searcher
-    .search(&qs.query, &qs.book_ids.unwrap_or_default())?;
+    .search(&qs.query, &qs.book_ids.unwrap_or_default(), &options)?;
   let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
 
   Ok(Response {
@@ -40,7 +68,9 @@ fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
 fn main() -> anyhow::Result<()> {
   let index_dir = Path::new("./data");
   let index = Rc::new(open_readonly_index(index_dir).map_err(|e| anyhow!("Opening index: {}", e))?);
-  let searcher = Searcher::new(&index)?;
+  let searcher = Searcher::new(&index)?
+    .with_synonyms(Synonyms::load(index_dir)?)
+    .with_catalog(&Catalog::load(index_dir)?);
 
   let ctx = Context {
     // index: index.clone(),
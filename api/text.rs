@@ -14,6 +14,7 @@ struct Qs {
   l0: usize,
   l1: Option<usize>,
   l2: Option<usize>,
+  words: Option<bool>,
 }
 
 struct Context<'a> {
@@ -25,7 +26,12 @@ fn handler(ctx: &Context, req: Request) -> Result<Response, RequestError> {
   let q = req.uri().query().unwrap_or("");
   let qs: Qs = serde_qs::Config::new(1, false).deserialize_str(q)?;
 
-  let results = ctx.searcher.get_text(&qs.book_id, qs.l0, qs.l1, qs.l2)?;
+  let options = TextOptions {
+    words: qs.words.unwrap_or(false),
+  };
+  let results = ctx
+    .searcher
+    .get_text(&qs.book_id, qs.l0, qs.l1, qs.l2, &options)?;
   let output = serde_json::to_string(&results).map_err(anyhow::Error::new)?;
 
   Ok(Response {
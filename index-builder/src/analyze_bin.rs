@@ -0,0 +1,663 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use study_map_index::books::{parse_osis_range, BookMap, Bounds, Location};
+use study_map_index::collocations::{collocations, CollocationMeasure, CollocationOptions};
+use study_map_index::concordance::{
+  concordance, frequencies, FrequencyLevel, TableFormat, TableWriter,
+};
+use study_map_index::corpus::Corpus;
+use study_map_index::keyness::{keyness, KeynessMeasure, KeynessOptions};
+use study_map_index::parallels::{Parallel, Parallels, Span};
+use study_map_index::postings::TextPostings;
+use study_map_index::quotations::{Quotation, Quotations};
+use study_map_index::shingles::{find_runs, shingles, RunOptions};
+use study_map_index::stats::{L0L1Stats, Stats};
+use study_map_index::topics::{TopicOptions, Topics};
+use study_map_index::vocabulary::Vocabulary;
+use study_map_index::index;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "analyze", about = "Analyses of indexed texts")]
+pub struct Config {
+  #[structopt(short, long, help = r#"Defaults to ./api/data"#)]
+  data: Option<PathBuf>,
+
+  #[structopt(subcommand)]
+  command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+  /// Find passages within a text that share long runs of words, such as Kings and
+  /// Chronicles or the synoptic gospels, and write them to parallels-<book_id>.json.
+  Parallels {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(
+      long,
+      default_value = "4",
+      help = "The number of words in each shingle"
+    )]
+    shingle_size: usize,
+
+    #[structopt(long, default_value = "8", help = "The shortest run of words to keep")]
+    min_len: usize,
+
+    #[structopt(
+      long,
+      default_value = "20",
+      help = "Skip shingles that appear more often than this, such as stock phrases"
+    )]
+    max_occurrences: usize,
+
+    #[structopt(
+      long,
+      help = "Only find parallels from this OSIS range, such as \"Sam-Kgs\""
+    )]
+    range: Option<String>,
+
+    #[structopt(
+      long,
+      help = "Only find parallels to this OSIS range, such as \"1Chr-2Chr\""
+    )]
+    against: Option<String>,
+  },
+
+  /// Find where a text, such as a commentary or sermon, quotes a bible verbatim or nearly
+  /// so, and write the quotations to quotations-<source_id>-<bible_id>.json.
+  Quotations {
+    #[structopt(short, long, help = "The text that quotes the bible")]
+    source_id: String,
+
+    #[structopt(short, long)]
+    bible_id: String,
+
+    #[structopt(
+      long,
+      default_value = "3",
+      help = "The number of words in each shingle"
+    )]
+    shingle_size: usize,
+
+    #[structopt(
+      long,
+      default_value = "0.5",
+      help = "The fraction of a verse range's shingles that must appear in the quoting passage"
+    )]
+    min_similarity: f32,
+
+    #[structopt(
+      long,
+      default_value = "3",
+      help = "The fewest shingles that must match, so that short verses need more than a phrase"
+    )]
+    min_shingles: usize,
+
+    #[structopt(
+      long,
+      default_value = "50",
+      help = "Skip shingles that appear in more verses than this, such as stock phrases"
+    )]
+    max_occurrences: usize,
+  },
+
+  /// Fit an LDA topic model over the chapters of a text, and write each chapter's topic
+  /// weights and each topic's top words to topics-<book_id>.json.
+  Topics {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(long, default_value = "20")]
+    topics: usize,
+
+    #[structopt(
+      long,
+      default_value = "200",
+      help = "The number of Gibbs sampling sweeps"
+    )]
+    iterations: usize,
+
+    #[structopt(
+      long,
+      default_value = "15",
+      help = "The number of words to keep per topic"
+    )]
+    top_words: usize,
+  },
+
+  /// List the words that are most characteristic of a range of a text, such as a book,
+  /// compared to the rest of the text or to another range.
+  Keyness {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(
+      short,
+      long,
+      help = "An OSIS reference such as John, Matt-Luke, or Gen.1-Gen.11"
+    )]
+    range: String,
+
+    #[structopt(
+      long,
+      help = "The range to compare against. Defaults to the rest of the text"
+    )]
+    reference: Option<String>,
+
+    #[structopt(
+      long,
+      default_value = "log_likelihood",
+      help = "log_likelihood or chi_square"
+    )]
+    measure: KeynessMeasure,
+
+    #[structopt(long, default_value = "50")]
+    limit: usize,
+
+    #[structopt(
+      long,
+      default_value = "3",
+      help = "The fewest times a word must appear in the range"
+    )]
+    min_freq: usize,
+  },
+
+  /// List the words that appear with a word more often than chance, within a verse or a
+  /// window of words.
+  Collocations {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(short, long)]
+    word: String,
+
+    #[structopt(
+      short,
+      long,
+      help = "An OSIS reference such as John, Matt-Luke, or Gen.1-Gen.11. Defaults to the whole text"
+    )]
+    range: Option<String>,
+
+    #[structopt(
+      long,
+      help = "Count words within this many words of the word, instead of in the same verse"
+    )]
+    window: Option<u32>,
+
+    #[structopt(long, default_value = "log_likelihood", help = "pmi or log_likelihood")]
+    measure: CollocationMeasure,
+
+    #[structopt(long, default_value = "50")]
+    limit: usize,
+
+    #[structopt(
+      long,
+      default_value = "3",
+      help = "The fewest times a word must appear with the word"
+    )]
+    min_freq: usize,
+  },
+
+  /// Count the vocabulary of a text and its books again, and write it to
+  /// vocabulary-<book_id>.json. The indexer does this for new texts.
+  Vocabulary {
+    #[structopt(short, long)]
+    book_id: String,
+  },
+
+  /// Write a table of how many times each term appears in each book or chapter of a text.
+  Frequencies {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(long, default_value = "chapter", help = "book or chapter")]
+    by: FrequencyLevel,
+
+    #[structopt(short, long, default_value = "csv", help = "csv or tsv")]
+    format: TableFormat,
+
+    #[structopt(short, long, parse(from_os_str), help = "Defaults to standard output")]
+    output: Option<PathBuf>,
+  },
+
+  /// Write a keyword-in-context concordance of a text, with a line for every occurrence of
+  /// each word.
+  Concordance {
+    #[structopt(short, long)]
+    book_id: String,
+
+    #[structopt(
+      short,
+      long,
+      help = "Only include these words. Defaults to every word in the text"
+    )]
+    words: Vec<String>,
+
+    #[structopt(
+      long,
+      default_value = "5",
+      help = "The number of words to show on each side"
+    )]
+    context: usize,
+
+    #[structopt(short, long, default_value = "csv", help = "csv or tsv")]
+    format: TableFormat,
+
+    #[structopt(short, long, parse(from_os_str), help = "Defaults to standard output")]
+    output: Option<PathBuf>,
+  },
+}
+
+struct QuotationOptions {
+  shingle_size: usize,
+  min_similarity: f32,
+  min_shingles: usize,
+  max_occurrences: usize,
+}
+
+/// The verses covered by a run of tokens.
+fn token_span(corpus: &Corpus, token_verses: &[usize], start: usize, len: usize) -> Span {
+  let location = |token: usize| corpus.verses[token_verses[token]].location;
+  Span {
+    start: location(start),
+    end: location(start + len - 1),
+  }
+}
+
+fn chapter(location: Location) -> (usize, usize) {
+  (location.l0, location.l1)
+}
+
+/// Find parallels within a text, optionally only between a passage in `range` and one in
+/// `against`, given in the text's coordinates.
+fn parallels(
+  index: &tantivy::Index,
+  book_id: &str,
+  range: Option<Bounds>,
+  against: Option<Bounds>,
+  options: &RunOptions,
+) -> Result<Vec<Parallel>> {
+  let corpus = Corpus::load(index, book_id, None)?;
+  let (tokens, token_verses) = corpus.tokens();
+  let shingles = shingles(&tokens, options.size);
+
+  let location = |i: usize| corpus.verses[token_verses[i]].location;
+  let in_bounds = |i: usize, bounds: Option<Bounds>| match bounds {
+    Some((start, end)) => {
+      let verse = location(i).first_verse();
+      verse >= start && verse <= end
+    }
+    None => true,
+  };
+  let between = |i: usize, j: usize| in_bounds(i, range) && in_bounds(j, against);
+
+  // Repeated phrases within a chapter, such as refrains, aren't parallels.
+  let runs = find_runs(&shingles, &shingles, true, options, |i, j| {
+    chapter(location(i)) != chapter(location(j)) && (between(i, j) || between(j, i))
+  });
+
+  let parallels = runs
+    .into_iter()
+    .map(|run| Parallel {
+      a: token_span(&corpus, &token_verses, run.a, run.len),
+      b: token_span(&corpus, &token_verses, run.b, run.len),
+      len: run.len,
+    })
+    .collect();
+
+  Ok(parallels)
+}
+
+/// Compare each passage of the source text against the bible. Verses that share shingles
+/// with the passage are grouped into runs of consecutive verses, and each run is scored by
+/// how much of it the passage contains, which allows for small changes in wording.
+fn quotations(
+  index: &tantivy::Index,
+  source_id: &str,
+  bible_id: &str,
+  options: &QuotationOptions,
+) -> Result<Vec<Quotation>> {
+  let bible = Corpus::load(index, bible_id, None)?;
+  // Tokenize both texts the same way so that their shingles can be compared.
+  let source = Corpus::load(index, source_id, Some(bible.analyzer))?;
+
+  // Shingles are taken within each verse, so that a verse quoted on its own can match
+  // completely.
+  let mut verse_shingles = Vec::with_capacity(bible.verses.len());
+  let mut shingle_verses: HashMap<u64, Vec<usize>> = HashMap::new();
+  for (i, verse) in bible.verses.iter().enumerate() {
+    let tokens = verse.tokens.iter().map(|t| t.as_str()).collect::<Vec<_>>();
+    let shingles = shingles(&tokens, options.shingle_size);
+    for shingle in &shingles {
+      let verses = shingle_verses.entry(*shingle).or_default();
+      if verses.last() != Some(&i) {
+        verses.push(i);
+      }
+    }
+    verse_shingles.push(shingles);
+  }
+
+  let mut quotations = Vec::new();
+  for passage in &source.verses {
+    let tokens = passage
+      .tokens
+      .iter()
+      .map(|t| t.as_str())
+      .collect::<Vec<_>>();
+    let passage_shingles = shingles(&tokens, options.shingle_size)
+      .into_iter()
+      .collect::<HashSet<_>>();
+
+    let mut hits = passage_shingles
+      .iter()
+      .filter_map(|shingle| shingle_verses.get(shingle))
+      .filter(|verses| verses.len() <= options.max_occurrences)
+      .flatten()
+      .copied()
+      .collect::<Vec<_>>();
+    hits.sort_unstable();
+    hits.dedup();
+
+    // Split the hits into runs of consecutive verses within a chapter.
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for verse in hits {
+      match groups.last_mut() {
+        Some(group)
+          if *group.last().unwrap() + 1 == verse
+            && chapter(bible.verses[verse - 1].location)
+              == chapter(bible.verses[verse].location) =>
+        {
+          group.push(verse)
+        }
+        _ => groups.push(vec![verse]),
+      }
+    }
+
+    for group in groups {
+      let group_shingles = group
+        .iter()
+        .flat_map(|&verse| verse_shingles[verse].iter())
+        .collect::<Vec<_>>();
+      let matched = group_shingles
+        .iter()
+        .filter(|shingle| passage_shingles.contains(shingle))
+        .count();
+      let similarity = matched as f32 / group_shingles.len().max(1) as f32;
+
+      if matched >= options.min_shingles && similarity >= options.min_similarity {
+        quotations.push(Quotation {
+          source: passage.location,
+          verses: Span {
+            start: bible.verses[group[0]].location,
+            end: bible.verses[group[group.len() - 1]].location,
+          },
+          similarity,
+        });
+      }
+    }
+  }
+
+  Ok(quotations)
+}
+
+fn table_writer(
+  format: TableFormat,
+  output: Option<PathBuf>,
+) -> Result<TableWriter<Box<dyn Write>>> {
+  let writer: Box<dyn Write> = match output {
+    Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+    None => Box::new(BufWriter::new(std::io::stdout())),
+  };
+  Ok(TableWriter::new(writer, format))
+}
+
+/// The stats for a text, which name its books and chapters.
+fn load_stats(data_path: &Path, book_id: &str) -> Result<L0L1Stats> {
+  let path = data_path.join(format!("stats-{}.json", book_id));
+  let f = File::open(&path).map_err(|e| anyhow!("Opening {}: {}", path.display(), e))?;
+  Ok(serde_json::from_reader(f)?)
+}
+
+fn main() -> Result<()> {
+  let config = Config::from_args();
+  let data_path = config
+    .data
+    .unwrap_or_else(|| std::env::current_dir().unwrap().join("api/data"));
+  let index = index::open_index(&data_path).map_err(|e| anyhow!("Opening index: {}", e))?;
+
+  match config.command {
+    Command::Parallels {
+      book_id,
+      shingle_size,
+      min_len,
+      max_occurrences,
+      range,
+      against,
+    } => {
+      let options = RunOptions {
+        size: shingle_size,
+        max_occurrences,
+        min_len,
+      };
+      let books = BookMap::load(&data_path, &book_id)?;
+      let parse_range = |r: &str| {
+        parse_osis_range(r)
+          .and_then(|bounds| books.range(bounds))
+          .ok_or_else(|| anyhow!("'{}' covers no books of {}", r, book_id))
+      };
+      let range = range.as_deref().map(parse_range).transpose()?;
+      let against = against.as_deref().map(parse_range).transpose()?;
+      let parallels = parallels(&index, &book_id, range, against, &options)?;
+      println!("Found {} parallels in {}", parallels.len(), book_id);
+      Parallels { book_id, parallels }.write(&data_path)?;
+    }
+    Command::Quotations {
+      source_id,
+      bible_id,
+      shingle_size,
+      min_similarity,
+      min_shingles,
+      max_occurrences,
+    } => {
+      let options = QuotationOptions {
+        shingle_size,
+        min_similarity,
+        min_shingles,
+        max_occurrences,
+      };
+      let quotations = quotations(&index, &source_id, &bible_id, &options)?;
+      println!(
+        "Found {} quotations of {} in {}",
+        quotations.len(),
+        bible_id,
+        source_id
+      );
+      Quotations {
+        source_id,
+        bible_id,
+        quotations,
+      }
+      .write(&data_path)?;
+    }
+    Command::Topics {
+      book_id,
+      topics,
+      iterations,
+      top_words,
+    } => {
+      let options = TopicOptions {
+        topics,
+        iterations,
+        top_words,
+        ..TopicOptions::default()
+      };
+      let corpus = Corpus::load(&index, &book_id, None)?;
+      let topics = Topics::fit(&corpus, &options)?;
+      for (i, topic) in topics.topics.iter().enumerate() {
+        let words = topic
+          .words
+          .iter()
+          .map(|w| w.word.as_str())
+          .collect::<Vec<_>>();
+        println!("{}: {}", i, words.join(" "));
+      }
+      topics.write(&data_path)?;
+    }
+    Command::Keyness {
+      book_id,
+      range,
+      reference,
+      measure,
+      limit,
+      min_freq,
+    } => {
+      let options = KeynessOptions {
+        measure,
+        limit,
+        min_freq,
+      };
+
+      let postings = TextPostings::load(&index, &book_id)?;
+      let books = BookMap::load(&data_path, &book_id)?;
+      let parse_range = |r: &str| {
+        parse_osis_range(r)
+          .and_then(|bounds| books.range(bounds))
+          .filter(|(start, end)| !postings.verse_range(*start, *end).is_empty())
+          .ok_or_else(|| anyhow!("'{}' covers no verses of {}", r, book_id))
+      };
+      let target = parse_range(&range)?;
+      let reference = reference.as_deref().map(parse_range).transpose()?;
+      let surface_forms = Corpus::load(&index, &book_id, None)?.surface_forms();
+      println!("word\tfreq\treference_freq\tlog_likelihood\tchi_square");
+      for word in keyness(&postings, &surface_forms, target, reference, &options) {
+        println!(
+          "{}\t{}\t{}\t{:.2}\t{:.2}",
+          word.word, word.freq, word.reference_freq, word.log_likelihood, word.chi_square
+        );
+      }
+    }
+    Command::Collocations {
+      book_id,
+      word,
+      range,
+      window,
+      measure,
+      limit,
+      min_freq,
+    } => {
+      let options = CollocationOptions {
+        measure,
+        window,
+        limit,
+        min_freq,
+      };
+
+      let postings = TextPostings::load(&index, &book_id)?;
+      let books = BookMap::load(&data_path, &book_id)?;
+      let range = range
+        .as_deref()
+        .map(|r| {
+          parse_osis_range(r)
+            .and_then(|bounds| books.range(bounds))
+            .filter(|(start, end)| !postings.verse_range(*start, *end).is_empty())
+            .ok_or_else(|| anyhow!("'{}' covers no verses of {}", r, book_id))
+        })
+        .transpose()?;
+      let node = postings
+        .analyze(&index, &word)?
+        .first()
+        .and_then(|term| postings.term(term))
+        .ok_or_else(|| anyhow!("'{}' does not appear in {}", word, book_id))?;
+      let surface_forms = Corpus::load(&index, &book_id, None)?.surface_forms();
+
+      println!("word\tcooccurrences\tfreq\tpmi\tlog_likelihood");
+      for c in collocations(&postings, &surface_forms, node, range, &options) {
+        println!(
+          "{}\t{}\t{}\t{:.2}\t{:.2}",
+          c.word, c.cooccurrences, c.freq, c.pmi, c.log_likelihood
+        );
+      }
+    }
+    Command::Vocabulary { book_id } => {
+      let book_names = load_stats(&data_path, &book_id)?
+        .children
+        .into_iter()
+        .map(|book| book.name)
+        .collect::<Vec<_>>();
+      let vocabulary = Vocabulary::build(&index, &book_id, &book_names)?;
+      println!(
+        "{}: {} words, {} distinct, {} hapax legomena",
+        book_id,
+        vocabulary.stats.tokens,
+        vocabulary.stats.types,
+        vocabulary.stats.hapax_legomena.len()
+      );
+      vocabulary.write(&data_path)?;
+    }
+    Command::Frequencies {
+      book_id,
+      by,
+      format,
+      output,
+    } => {
+      let postings = TextPostings::load(&index, &book_id)?;
+      let surface_forms = Corpus::load(&index, &book_id, None)?.surface_forms();
+      let stats = load_stats(&data_path, &book_id)?;
+
+      let mut table = table_writer(format, output)?;
+      table.write_row(&["term", "word", "reference", "l0", "l1", "freq"])?;
+      for f in frequencies(&postings, by) {
+        let term = f.term.term.as_str();
+        table.write_row(&[
+          term,
+          surface_forms.get(term).map(|w| w.as_str()).unwrap_or(term),
+          &stats.describe(Some(f.l0), f.l1, None),
+          &f.l0.to_string(),
+          &f.l1.map(|l1| l1.to_string()).unwrap_or_default(),
+          &f.freq.to_string(),
+        ])?;
+      }
+      table.into_inner().flush()?;
+    }
+    Command::Concordance {
+      book_id,
+      words,
+      context,
+      format,
+      output,
+    } => {
+      let postings = TextPostings::load(&index, &book_id)?;
+      let corpus = Corpus::load(&index, &book_id, None)?;
+      let stats = load_stats(&data_path, &book_id)?;
+
+      // Look the words up by the terms that the text's analyzer makes of them.
+      let mut terms = HashSet::new();
+      for word in &words {
+        terms.extend(postings.analyze(&index, word)?);
+      }
+
+      let mut table = table_writer(format, output)?;
+      table.write_row(&["term", "reference", "left", "keyword", "right"])?;
+      for line in concordance(&postings, &corpus, context, |term| {
+        words.is_empty() || terms.contains(term)
+      }) {
+        let location = line.location;
+        table.write_row(&[
+          line.term.term.as_str(),
+          &stats.describe(Some(location.l0), Some(location.l1), location.l2),
+          &line.left,
+          line.keyword,
+          &line.right,
+        ])?;
+      }
+      table.into_inner().flush()?;
+    }
+  }
+
+  Ok(())
+}
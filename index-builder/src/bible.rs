@@ -1,14 +1,18 @@
 use anyhow::{anyhow, Result};
-use stats::{L0L1Stats, Stats};
 use std::fs::File;
 use std::path::PathBuf;
 use structopt::StructOpt;
-use tantivy::doc;
+use study_map_index::index::{DocKind, Word};
+use study_map_index::normalize_strong;
+use study_map_index::stats::{L0L1Stats, Stats};
+use study_map_index::{index, morphology, vectors, vocabulary};
+use tantivy::{
+    doc,
+    schema::{Document, Field},
+    tokenizer::{PreTokenizedString, Token},
+};
 
-mod index;
 mod read_bible;
-mod readonly_dir_wrapper;
-mod stats;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "bible-indexer")]
@@ -34,6 +38,159 @@ pub struct Config {
 
     #[structopt(short, long, help = r#"Defaults to ./api/data"#)]
     output: Option<PathBuf>,
+
+    #[structopt(
+        short,
+        long,
+        default_value = "standard",
+        help = r#"How to tokenize the text. "standard" for English, "archaic" for
+            KJV-era English, or "cjk" for Chinese, Japanese, and Korean translations"#
+    )]
+    analyzer: index::Analyzer,
+
+    #[structopt(
+        long,
+        help = r#"Also build TF-IDF/LSA vectors for the verses and chapters, for finding
+            related passages"#
+    )]
+    vectors: bool,
+}
+
+/// A run of verses that is also indexed as a single document.
+struct Section {
+    l0: usize,
+    l1: usize,
+    l2: usize,
+    end: (usize, usize),
+    heading: Option<String>,
+    text: String,
+    red_letter: Vec<(usize, usize)>,
+    words: Vec<Word>,
+}
+
+impl Section {
+    fn new(l0: usize, l1: usize, l2: usize, heading: Option<String>) -> Self {
+        Section {
+            l0,
+            l1,
+            l2,
+            end: (l1, l2),
+            heading,
+            text: String::new(),
+            red_letter: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, l1: usize, l2: usize, passage: &read_bible::Passage) {
+        self.end = (l1, l2);
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+
+        let offset = self.text.len();
+        self.text.push_str(&passage.text);
+        self.red_letter.extend(
+            passage
+                .red_letter
+                .iter()
+                .map(|(start, end)| (start + offset, end + offset)),
+        );
+        self.words.extend(passage.words.iter().map(|word| Word {
+            start: word.start + offset,
+            end: word.end + offset,
+            ..word.clone()
+        }));
+    }
+}
+
+/// Index the Strong's numbers and lemmas of the tagged words in a text, and store the words
+/// so that matching tags can be highlighted in the text.
+fn add_tags(doc: &mut Document, fields: &TagFields, text: &str, words: &[Word]) -> Result<()> {
+    if words.is_empty() {
+        return Ok(());
+    }
+
+    let tag_tokens = |tags: &dyn Fn(&Word) -> Vec<String>| {
+        let tokens = words
+            .iter()
+            .enumerate()
+            .flat_map(|(position, word)| {
+                tags(word).into_iter().map(move |tag| Token {
+                    offset_from: word.start,
+                    offset_to: word.end,
+                    position,
+                    text: tag,
+                    position_length: 1,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        PreTokenizedString {
+            text: String::from(text),
+            tokens,
+        }
+    };
+
+    let strong = tag_tokens(&|word| {
+        word.strong
+            .iter()
+            .map(|n| normalize_strong(n).unwrap_or_else(|| n.clone()))
+            .collect()
+    });
+    if !strong.tokens.is_empty() {
+        doc.add_pre_tokenized_text(fields.strong, &strong);
+    }
+
+    let lemma = tag_tokens(&|word| word.lemma.iter().map(|l| l.to_lowercase()).collect());
+    if !lemma.tokens.is_empty() {
+        doc.add_pre_tokenized_text(fields.lemma, &lemma);
+    }
+
+    let morph = tag_tokens(&morphology::word_tokens);
+    if !morph.tokens.is_empty() {
+        doc.add_pre_tokenized_text(fields.morph, &morph);
+    }
+
+    doc.add_text(fields.words, &serde_json::to_string(words)?);
+    Ok(())
+}
+
+struct TagFields {
+    strong: Field,
+    lemma: Field,
+    morph: Field,
+    words: Field,
+}
+
+struct RedLetterFields {
+    spans: Field,
+    red: Field,
+    black: Field,
+}
+
+/// Store red letter spans as a flat list of start and end offsets, and index the red letter
+/// text and the rest of the text into their own fields.
+fn add_red_letter(
+    doc: &mut Document,
+    fields: &RedLetterFields,
+    text: &str,
+    spans: &[(usize, usize)],
+) {
+    let mut red = Vec::new();
+    let mut black = Vec::new();
+    let mut last_end = 0;
+    for &(start, end) in spans {
+        doc.add_u64(fields.spans, start as u64);
+        doc.add_u64(fields.spans, end as u64);
+        black.push(&text[last_end..start]);
+        red.push(&text[start..end]);
+        last_end = end;
+    }
+    black.push(&text[last_end..]);
+
+    doc.add_text(fields.red, &red.join("\n"));
+    doc.add_text(fields.black, &black.join("\n"));
 }
 
 fn main() -> Result<()> {
@@ -44,6 +201,8 @@ fn main() -> Result<()> {
         file,
         title: title_arg,
         output,
+        analyzer,
+        vectors,
     } = config;
 
     let title = title_arg.unwrap_or_else(|| {
@@ -67,40 +226,191 @@ fn main() -> Result<()> {
     let l0_field = schema.get_field("l0").unwrap();
     let l1_field = schema.get_field("l1").unwrap();
     let l2_field = schema.get_field("l2").unwrap();
-    let text_field = schema.get_field("text").unwrap();
+    let text_field = schema.get_field(analyzer.field_name()).unwrap();
     let doc_id_field = schema.get_field("doc_id").unwrap();
     let book_id_field = schema.get_field("book").unwrap();
+    let kind_field = schema.get_field("kind").unwrap();
+    let end_l1_field = schema.get_field("end_l1").unwrap();
+    let end_l2_field = schema.get_field("end_l2").unwrap();
+    let heading_field = schema.get_field(analyzer.heading_field_name()).unwrap();
+    let red_letter_fields = RedLetterFields {
+        spans: schema.get_field("red_letter").unwrap(),
+        red: schema
+            .get_field(analyzer.red_letter_field_name(true))
+            .unwrap(),
+        black: schema
+            .get_field(analyzer.red_letter_field_name(false))
+            .unwrap(),
+    };
+    let tag_fields = TagFields {
+        strong: schema.get_field("strong").unwrap(),
+        lemma: schema.get_field("lemma").unwrap(),
+        morph: schema.get_field("morph").unwrap(),
+        words: schema.get_field("words").unwrap(),
+    };
+
+    // Notes and pericopes are keyed by position, so a reindexed text with fewer of them would
+    // leave the old ones behind. Start the book from scratch instead.
+    writer.delete_term(tantivy::Term::from_field_text(book_id_field, &book_id));
 
     let mut stats = L0L1Stats::new(title.clone());
 
+    // Each chapter is also indexed as a whole, so that coarser searches can rank chapters.
+    let add_chapter = |chapter: Section| -> Result<()> {
+        let doc_id = format!("{}-{}-{}", book_id, chapter.l0, chapter.l1);
+        writer.delete_term(tantivy::Term::from_field_text(doc_id_field, &doc_id));
+        let mut doc = doc!(
+            doc_id_field => doc_id,
+            book_id_field => book_id.clone(),
+            kind_field => DocKind::Chapter.as_str(),
+            l0_field => chapter.l0 as u64,
+            l1_field => chapter.l1 as u64,
+        );
+        add_tags(&mut doc, &tag_fields, &chapter.text, &chapter.words)?;
+        doc.add_text(text_field, &chapter.text);
+        add_red_letter(
+            &mut doc,
+            &red_letter_fields,
+            &chapter.text,
+            &chapter.red_letter,
+        );
+        writer.add_document(doc);
+        Ok(())
+    };
+
+    // Likewise for the verses under each section heading.
+    let add_pericope = |pericope: Section| -> Result<()> {
+        let doc_id = format!(
+            "{}-{}-{}-{}-pericope",
+            book_id, pericope.l0, pericope.l1, pericope.l2
+        );
+        writer.delete_term(tantivy::Term::from_field_text(doc_id_field, &doc_id));
+        let mut doc = doc!(
+            doc_id_field => doc_id,
+            book_id_field => book_id.clone(),
+            kind_field => DocKind::Pericope.as_str(),
+            l0_field => pericope.l0 as u64,
+            l1_field => pericope.l1 as u64,
+            l2_field => pericope.l2 as u64,
+            end_l1_field => pericope.end.0 as u64,
+            end_l2_field => pericope.end.1 as u64,
+            heading_field => pericope.heading.unwrap_or_default(),
+        );
+        add_tags(&mut doc, &tag_fields, &pericope.text, &pericope.words)?;
+        doc.add_text(text_field, &pericope.text);
+        add_red_letter(
+            &mut doc,
+            &red_letter_fields,
+            &pericope.text,
+            &pericope.red_letter,
+        );
+        writer.add_document(doc);
+        Ok(())
+    };
+
+    let mut current_chapter: Option<Section> = None;
+    let mut current_pericope: Option<Section> = None;
+
     read_bible::read(&file, |passage| {
         let book_index = passage.book_index - 1;
         let chapter = passage.chapter - 1;
         let verse = passage.verse - 1;
         let doc_id = format!("{}-{}-{}-{}", book_id, book_index, chapter, verse);
 
+        match current_chapter.as_mut() {
+            Some(c) if c.l0 == book_index && c.l1 == chapter => {
+                c.push(chapter, verse, &passage);
+            }
+            _ => {
+                let mut new_chapter = Section::new(book_index, chapter, verse, None);
+                new_chapter.push(chapter, verse, &passage);
+                if let Some(c) = current_chapter.replace(new_chapter) {
+                    add_chapter(c)?;
+                }
+            }
+        }
+
+        // A pericope runs until the next heading or the end of the book.
+        match current_pericope.as_mut() {
+            Some(p) if p.l0 == book_index && passage.heading.is_none() => {
+                p.push(chapter, verse, &passage);
+            }
+            _ => {
+                let new_pericope = passage.heading.as_ref().map(|heading| {
+                    stats.add_range(book_index, chapter, Some(verse), heading);
+                    let mut pericope =
+                        Section::new(book_index, chapter, verse, Some(heading.clone()));
+                    pericope.push(chapter, verse, &passage);
+                    pericope
+                });
+                if let Some(p) = std::mem::replace(&mut current_pericope, new_pericope) {
+                    add_pericope(p)?;
+                }
+            }
+        }
+
         stats.add(
             book_index,
             Some(passage.book.as_ref()),
             passage.chapter - 1,
             None,
-            None,
+            Some(verse),
             passage.text.as_ref(),
         );
+        stats.add_red_letter(
+            book_index,
+            chapter,
+            passage
+                .red_letter
+                .iter()
+                .map(|(start, end)| end - start)
+                .sum(),
+        );
+
+        for (i, note) in passage.notes.into_iter().enumerate() {
+            let note_id = format!("{}-note-{}", doc_id, i);
+            writer.delete_term(tantivy::Term::from_field_text(doc_id_field, &note_id));
+            writer.add_document(doc!(
+                doc_id_field => note_id,
+                book_id_field => book_id.clone(),
+                kind_field => DocKind::Note.as_str(),
+                l0_field => book_index as u64,
+                l1_field => chapter as u64,
+                l2_field => verse as u64,
+                text_field => note,
+            ));
+        }
 
         writer.delete_term(tantivy::Term::from_field_text(doc_id_field, &doc_id));
-        writer.add_document(doc!(
+        let mut doc = doc!(
             doc_id_field => doc_id,
             book_id_field => book_id.clone(),
+            kind_field => DocKind::Verse.as_str(),
             l0_field => book_index as u64,
             l1_field=> chapter as u64,
             l2_field => verse as u64,
-            text_field => passage.text,
-        ));
+        );
+        add_tags(&mut doc, &tag_fields, &passage.text, &passage.words)?;
+        doc.add_text(text_field, &passage.text);
+        add_red_letter(
+            &mut doc,
+            &red_letter_fields,
+            &passage.text,
+            &passage.red_letter,
+        );
+        writer.add_document(doc);
 
         Ok(())
     })?;
 
+    if let Some(c) = current_chapter {
+        add_chapter(c)?;
+    }
+
+    if let Some(p) = current_pericope {
+        add_pericope(p)?;
+    }
+
     writer.commit().map_err(|e| anyhow!("{}", e))?;
 
     let meta_file = File::create(format!(
@@ -112,10 +422,23 @@ fn main() -> Result<()> {
     meta_file.sync_all()?;
     drop(meta_file);
 
+    let book_names = stats
+        .children
+        .iter()
+        .map(|book| book.name.clone())
+        .collect::<Vec<_>>();
+    vocabulary::Vocabulary::build(&ind, &book_id, &book_names)?.write(&data_path)?;
+
+    if vectors {
+        vectors::VectorSet::build(&ind, &book_id, &vectors::VectorOptions::default())?
+            .write(&data_path)?;
+    }
+
     let mut catalog = index::Catalog::load(&data_path)?;
     catalog.add(index::CatalogItem {
         id: book_id,
         name: title,
+        analyzer,
     });
 
     catalog.write(&data_path)?;
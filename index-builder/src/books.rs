@@ -0,0 +1,441 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+/// The OSIS abbreviations of the books of the Protestant canon, in order. A book's position
+/// in this list is its canonical index, which cross references are stored by. The l0
+/// coordinate of a book in an indexed text is instead its position in the text's source
+/// file, and `BookMap` converts between the two.
+pub const OSIS_BOOKS: &[&str] = &[
+  "Gen", "Exod", "Lev", "Num", "Deut", "Josh", "Judg", "Ruth", "1Sam", "2Sam", "1Kgs", "2Kgs",
+  "1Chr", "2Chr", "Ezra", "Neh", "Esth", "Job", "Ps", "Prov", "Eccl", "Song", "Isa", "Jer", "Lam",
+  "Ezek", "Dan", "Hos", "Joel", "Amos", "Obad", "Jonah", "Mic", "Nah", "Hab", "Zeph", "Hag",
+  "Zech", "Mal", "Matt", "Mark", "Luke", "John", "Acts", "Rom", "1Cor", "2Cor", "Gal", "Eph",
+  "Phil", "Col", "1Thess", "2Thess", "1Tim", "2Tim", "Titus", "Phlm", "Heb", "Jas", "1Pet", "2Pet",
+  "1John", "2John", "3John", "Jude", "Rev",
+];
+
+/// The English names of the books in `OSIS_BOOKS`, and other names that they go by, with
+/// spaces and punctuation removed and numbers as digits.
+const BOOK_NAMES: &[&[&str]] = &[
+  &["genesis"],
+  &["exodus"],
+  &["leviticus"],
+  &["numbers"],
+  &["deuteronomy"],
+  &["joshua"],
+  &["judges"],
+  &["ruth"],
+  &["1samuel", "1kingdoms"],
+  &["2samuel", "2kingdoms"],
+  &["1kings", "3kingdoms"],
+  &["2kings", "4kingdoms"],
+  &["1chronicles", "1paralipomenon"],
+  &["2chronicles", "2paralipomenon"],
+  &["ezra"],
+  &["nehemiah"],
+  &["esther"],
+  &["job"],
+  &["psalms", "psalm"],
+  &["proverbs"],
+  &["ecclesiastes", "qoheleth"],
+  &["songofsolomon", "songofsongs", "canticles"],
+  &["isaiah"],
+  &["jeremiah"],
+  &["lamentations"],
+  &["ezekiel"],
+  &["daniel"],
+  &["hosea"],
+  &["joel"],
+  &["amos"],
+  &["obadiah"],
+  &["jonah"],
+  &["micah"],
+  &["nahum"],
+  &["habakkuk"],
+  &["zephaniah"],
+  &["haggai"],
+  &["zechariah"],
+  &["malachi"],
+  &["matthew"],
+  &["mark"],
+  &["luke"],
+  &["john"],
+  &["acts", "actsoftheapostles"],
+  &["romans"],
+  &["1corinthians"],
+  &["2corinthians"],
+  &["galatians"],
+  &["ephesians"],
+  &["philippians"],
+  &["colossians"],
+  &["1thessalonians"],
+  &["2thessalonians"],
+  &["1timothy"],
+  &["2timothy"],
+  &["titus"],
+  &["philemon"],
+  &["hebrews"],
+  &["james"],
+  &["1peter"],
+  &["2peter"],
+  &["1john"],
+  &["2john"],
+  &["3john"],
+  &["jude"],
+  &["revelation", "revelationofjohn", "apocalypse"],
+];
+
+/// The canonical index of an OSIS book abbreviation.
+pub fn book_index(osis: &str) -> Option<usize> {
+  OSIS_BOOKS
+    .iter()
+    .position(|book| book.eq_ignore_ascii_case(osis))
+}
+
+/// The canonical index of a book from its name in a text, such as "Genesis", "1 Kings",
+/// "I Kings", or "Gen".
+pub fn book_name_index(name: &str) -> Option<usize> {
+  let mut words = name
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|w| !w.is_empty())
+    .map(|w| w.to_lowercase())
+    .collect::<Vec<_>>();
+  if words.len() > 1 {
+    let number = match words[0].as_str() {
+      "i" | "first" => Some("1"),
+      "ii" | "second" => Some("2"),
+      "iii" | "third" => Some("3"),
+      _ => None,
+    };
+    if let Some(number) = number {
+      words[0] = String::from(number);
+    }
+  }
+  let name = words.concat();
+
+  book_index(&name).or_else(|| {
+    BOOK_NAMES
+      .iter()
+      .position(|names| names.contains(&name.as_str()))
+  })
+}
+
+/// The parts of a text's stats file that name its books.
+#[derive(Deserialize)]
+struct StatsBooks {
+  children: Vec<StatsBook>,
+}
+
+#[derive(Deserialize)]
+struct StatsBook {
+  #[serde(default)]
+  name: String,
+}
+
+/// Converts between the l0 coordinates of a text, which follow the order of the books in
+/// its source file, and the canonical indexes of `OSIS_BOOKS`. Books that aren't in the
+/// canon, or whose names aren't recognized, have no canonical index.
+#[derive(Debug, Clone, Default)]
+pub struct BookMap {
+  /// The canonical index of each l0.
+  canonical: Vec<Option<usize>>,
+}
+
+impl BookMap {
+  /// Map books by their names, in l0 order.
+  pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> BookMap {
+    let mut canonical: Vec<Option<usize>> = Vec::new();
+    for name in names {
+      // When a name appears more than once, such as for the Greek additions to Esther, the
+      // first book keeps it.
+      let index = book_name_index(name).filter(|i| !canonical.contains(&Some(*i)));
+      canonical.push(index);
+    }
+
+    BookMap { canonical }
+  }
+
+  /// Read the book names from the text's stats file in the data directory.
+  pub fn load(data_path: &Path, book_id: &str) -> Result<BookMap> {
+    let path = data_path.join(format!("stats-{}.json", book_id));
+    let f = File::open(&path).map_err(|e| anyhow!("Opening {}: {}", path.display(), e))?;
+    let stats: StatsBooks = serde_json::from_reader(f)?;
+    Ok(BookMap::new(stats.children.iter().map(|b| b.name.as_str())))
+  }
+
+  /// The text's l0 for a canonical book.
+  pub fn l0(&self, canonical: usize) -> Option<usize> {
+    self.canonical.iter().position(|&c| c == Some(canonical))
+  }
+
+  /// The canonical book for one of the text's l0 coordinates.
+  pub fn canonical(&self, l0: usize) -> Option<usize> {
+    self.canonical.get(l0).copied().flatten()
+  }
+
+  /// Convert a location from canonical coordinates into the text's.
+  pub fn to_text(&self, location: Location) -> Option<Location> {
+    Some(Location {
+      l0: self.l0(location.l0)?,
+      ..location
+    })
+  }
+
+  /// Convert canonical bounds, such as those from `parse_osis_range`, into the text's
+  /// coordinates. The result runs from the first to the last of the text's books that are
+  /// in the range, keeping the chapter and verse of an edge only when the text has the book
+  /// that the range starts or ends in. Returns None when the text has none of the books.
+  pub fn range(&self, bounds: Bounds) -> Option<Bounds> {
+    let (start, end) = bounds;
+    let in_range = self
+      .canonical
+      .iter()
+      .enumerate()
+      .filter_map(|(l0, c)| c.filter(|c| (start.0..=end.0).contains(c)).map(|c| (l0, c)))
+      .collect::<Vec<_>>();
+    let &(first_l0, first) = in_range.iter().min_by_key(|(_, c)| *c)?;
+    let &(last_l0, last) = in_range.iter().max_by_key(|(_, c)| *c)?;
+
+    let text_start = if first == start.0 {
+      (first_l0, start.1, start.2)
+    } else {
+      (first_l0, 0, 0)
+    };
+    let text_end = if last == end.0 {
+      (last_l0, end.1, end.2)
+    } else {
+      (last_l0, usize::MAX, usize::MAX)
+    };
+    if text_start > text_end {
+      return None;
+    }
+
+    Some((text_start, text_end))
+  }
+
+  /// Convert a location from the text's coordinates into canonical ones.
+  pub fn to_canonical(&self, location: Location) -> Option<Location> {
+    Some(Location {
+      l0: self.canonical(location.l0)?,
+      ..location
+    })
+  }
+}
+
+/// A verse, or a whole chapter when l2 is None, in canonical coordinates.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+  pub l0: usize,
+  pub l1: usize,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub l2: Option<usize>,
+}
+
+impl Location {
+  /// The first verse of the location, for sorting and comparing.
+  pub fn first_verse(&self) -> (usize, usize, usize) {
+    (self.l0, self.l1, self.l2.unwrap_or(0))
+  }
+
+  /// The last verse of the location, where a whole chapter ends after every verse.
+  pub fn last_verse(&self) -> (usize, usize, usize) {
+    (self.l0, self.l1, self.l2.unwrap_or(usize::MAX))
+  }
+}
+
+/// The first and last (l0, l1, l2) coordinates of a range of verses in one book. Without
+/// an end, the range covers the verse, chapter, or book that starts it.
+pub fn range_bounds(
+  l0: usize,
+  l1: Option<usize>,
+  l2: Option<usize>,
+  end_l1: Option<usize>,
+  end_l2: Option<usize>,
+) -> ((usize, usize, usize), (usize, usize, usize)) {
+  let start = (l0, l1.unwrap_or(0), l2.unwrap_or(0));
+  let end_l2 = match (end_l1, end_l2) {
+    (_, Some(end_l2)) => Some(end_l2),
+    (Some(_), None) => None,
+    (None, None) => l2,
+  };
+  let end = (
+    l0,
+    end_l1.or(l1).unwrap_or(usize::MAX),
+    end_l2.unwrap_or(usize::MAX),
+  );
+
+  (start, end)
+}
+
+/// Parse an OSIS reference such as "Gen.1.1", "Gen.1", "Gen.1.1-Gen.1.3", or the
+/// abbreviated "Gen.1.1-3", into its first and last locations.
+pub fn parse_osis_ref(reference: &str) -> Option<(Location, Location)> {
+  let mut parts = reference.splitn(2, '-');
+  let start = parse_osis_location(parts.next()?, None)?;
+  let end = match parts.next() {
+    Some(end) => parse_osis_location(end, Some(start))?,
+    None => start,
+  };
+
+  Some((start, end))
+}
+
+/// Parse one side of an OSIS reference. The end of a range may leave out the book, or the
+/// book and chapter, which are then taken from the start.
+fn parse_osis_location(s: &str, start: Option<Location>) -> Option<Location> {
+  let parts = s.split('.').collect::<Vec<_>>();
+  let number = |s: &str| s.parse::<usize>().ok().filter(|&n| n > 0).map(|n| n - 1);
+
+  match (book_index(parts[0]), start, parts.len()) {
+    (Some(l0), _, 2) => Some(Location {
+      l0,
+      l1: number(parts[1])?,
+      l2: None,
+    }),
+    (Some(l0), _, 3) => Some(Location {
+      l0,
+      l1: number(parts[1])?,
+      l2: Some(number(parts[2])?),
+    }),
+    (None, Some(start), 1) if start.l2.is_some() => Some(Location {
+      l2: Some(number(parts[0])?),
+      ..start
+    }),
+    (None, Some(start), 1) => Some(Location {
+      l1: number(parts[0])?,
+      ..start
+    }),
+    (None, Some(start), 2) => Some(Location {
+      l0: start.l0,
+      l1: number(parts[0])?,
+      l2: Some(number(parts[1])?),
+    }),
+    _ => None,
+  }
+}
+
+/// The (l0, l1, l2) coordinates of the first and last verses of a range.
+pub type Bounds = ((usize, usize, usize), (usize, usize, usize));
+
+/// Parse an OSIS reference into the first and last (l0, l1, l2) coordinates that it covers.
+/// Besides the forms that `parse_osis_ref` reads, this takes whole books, such as "John",
+/// and ranges of books, such as "Matt-Luke".
+pub fn parse_osis_range(reference: &str) -> Option<Bounds> {
+  let whole_book = |s: &str| book_index(s).filter(|_| !s.contains('.'));
+  let mut parts = reference.splitn(2, '-');
+  let start = whole_book(parts.next()?);
+
+  match (start, parts.next()) {
+    (Some(l0), None) => Some(((l0, 0, 0), (l0, usize::MAX, usize::MAX))),
+    (Some(l0), Some(end)) => {
+      let end_l0 = whole_book(end)?;
+      Some(((l0, 0, 0), (end_l0, usize::MAX, usize::MAX)))
+    }
+    (None, _) => {
+      parse_osis_ref(reference).map(|(start, end)| (start.first_verse(), end.last_verse()))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn location(l0: usize, l1: usize, l2: Option<usize>) -> Location {
+    Location { l0, l1, l2 }
+  }
+
+  #[test]
+  fn parse_osis_ref_reads_verses_and_ranges() {
+    assert_eq!(
+      parse_osis_ref("Gen.1.1"),
+      Some((location(0, 0, Some(0)), location(0, 0, Some(0))))
+    );
+    assert_eq!(
+      parse_osis_ref("John.3"),
+      Some((location(42, 2, None), location(42, 2, None)))
+    );
+    assert_eq!(
+      parse_osis_ref("Gen.1.1-Gen.1.3"),
+      Some((location(0, 0, Some(0)), location(0, 0, Some(2))))
+    );
+    assert_eq!(
+      parse_osis_ref("Gen.1.1-3"),
+      Some((location(0, 0, Some(0)), location(0, 0, Some(2))))
+    );
+    assert_eq!(
+      parse_osis_ref("Gen.1.30-2.3"),
+      Some((location(0, 0, Some(29)), location(0, 1, Some(2))))
+    );
+    assert_eq!(parse_osis_ref("Nope.1.1"), None);
+    assert_eq!(parse_osis_ref("Gen.0.1"), None);
+  }
+
+  #[test]
+  fn parse_osis_range_reads_books() {
+    let max = usize::MAX;
+    assert_eq!(parse_osis_range("John"), Some(((42, 0, 0), (42, max, max))));
+    assert_eq!(
+      parse_osis_range("Matt-Luke"),
+      Some(((39, 0, 0), (41, max, max)))
+    );
+    assert_eq!(parse_osis_range("John.3"), Some(((42, 2, 0), (42, 2, max))));
+    assert_eq!(
+      parse_osis_range("John.3.16"),
+      Some(((42, 2, 15), (42, 2, 15)))
+    );
+    assert_eq!(parse_osis_range("Matt-Luke.1"), None);
+  }
+
+  #[test]
+  fn book_names_find_canonical_books() {
+    assert_eq!(book_name_index("Genesis"), Some(0));
+    assert_eq!(book_name_index("Gen"), Some(0));
+    assert_eq!(book_name_index("1 Kings"), Some(10));
+    assert_eq!(book_name_index("I Kings"), Some(10));
+    assert_eq!(book_name_index("III John"), Some(63));
+    assert_eq!(book_name_index("Song of Solomon"), Some(21));
+    assert_eq!(book_name_index("Revelation of John"), Some(65));
+    assert_eq!(book_name_index("Tobit"), None);
+  }
+
+  #[test]
+  fn book_map_converts_ranges() {
+    let max = usize::MAX;
+    let books = BookMap::new(vec!["Matthew", "Mark", "Luke", "John"]);
+    assert_eq!(
+      books.range(parse_osis_range("Mark").unwrap()),
+      Some(((1, 0, 0), (1, max, max)))
+    );
+    assert_eq!(
+      books.range(parse_osis_range("John.3.16").unwrap()),
+      Some(((3, 2, 15), (3, 2, 15)))
+    );
+    // The text has no Acts, so the range ends at the end of John.
+    assert_eq!(
+      books.range(parse_osis_range("Luke.2-Acts.1").unwrap()),
+      Some(((2, 1, 0), (3, max, max)))
+    );
+    assert_eq!(books.range(parse_osis_range("Gen-Mal").unwrap()), None);
+  }
+
+  #[test]
+  fn book_map_converts_coordinates() {
+    let books = BookMap::new(vec!["Matthew", "Tobit", "Genesis"]);
+    assert_eq!(books.l0(0), Some(2));
+    assert_eq!(books.l0(39), Some(0));
+    assert_eq!(books.l0(1), None);
+    assert_eq!(books.canonical(1), None);
+    assert_eq!(books.canonical(2), Some(0));
+    assert_eq!(
+      books.to_text(location(39, 4, Some(2))),
+      Some(location(0, 4, Some(2)))
+    );
+    assert_eq!(
+      books.to_canonical(location(2, 0, None)),
+      Some(location(0, 0, None))
+    );
+  }
+}
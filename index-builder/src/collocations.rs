@@ -0,0 +1,288 @@
+use crate::books::Bounds;
+use crate::postings::{Posting, TermPostings, TextPostings};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// How to score the association between two words.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollocationMeasure {
+  /// Pointwise mutual information, which favors rare words that are seen together.
+  Pmi,
+  /// Dunning's log-likelihood ratio, which favors words that are seen together often.
+  LogLikelihood,
+}
+
+impl FromStr for CollocationMeasure {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    match s {
+      "pmi" => Ok(CollocationMeasure::Pmi),
+      "log_likelihood" => Ok(CollocationMeasure::LogLikelihood),
+      _ => Err(anyhow!("Unknown collocation measure '{}'", s)),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct CollocationOptions {
+  pub measure: CollocationMeasure,
+  /// Count words within this many positions of the term, instead of anywhere in the
+  /// same verse.
+  pub window: Option<u32>,
+  pub limit: usize,
+  /// Words seen with the term fewer times than this are left out, since PMI in particular
+  /// overrates rare pairs.
+  pub min_freq: usize,
+}
+
+impl Default for CollocationOptions {
+  fn default() -> Self {
+    CollocationOptions {
+      measure: CollocationMeasure::LogLikelihood,
+      window: None,
+      limit: 50,
+      min_freq: 3,
+    }
+  }
+}
+
+/// A word that appears with the term more often than chance.
+#[derive(Serialize, Debug)]
+pub struct Collocate {
+  /// The indexed term.
+  pub term: String,
+  /// The most common form of the term in the text.
+  pub word: String,
+  /// The number of verses with both words, or of times the word is within the window of
+  /// the term. A word near more than one use of the term is counted once.
+  pub cooccurrences: usize,
+  /// The number of verses with the word, or of times it appears, in the range.
+  pub freq: usize,
+  pub pmi: f64,
+  pub log_likelihood: f64,
+}
+
+/// The log-likelihood ratio of a 2x2 contingency table, from the count of both words `o11`,
+/// the counts of each word `r1` and `c1`, and the total `n`.
+fn log_likelihood(o11: f64, r1: f64, c1: f64, n: f64) -> f64 {
+  let observed = [o11, r1 - o11, c1 - o11, (n - r1 - c1 + o11).max(0.0)];
+  let expected = [
+    r1 * c1 / n,
+    r1 * (n - c1) / n,
+    (n - r1) * c1 / n,
+    (n - r1) * (n - c1) / n,
+  ];
+
+  2.0
+    * observed
+      .iter()
+      .zip(&expected)
+      .map(|(&o, &e)| {
+        if o > 0.0 && e > 0.0 {
+          o * (o / e).ln()
+        } else {
+          0.0
+        }
+      })
+      .sum::<f64>()
+}
+
+/// The number of positions in `b` that are no more than `window` from a position in `a`.
+/// Each position in `b` is counted once, so the count never exceeds its length.
+fn window_matches(a: &[u32], b: &[u32], window: u32) -> usize {
+  b.iter()
+    .filter(|&&q| {
+      a.iter()
+        .any(|&p| p != q && (p as i64 - q as i64).abs() <= window as i64)
+    })
+    .count()
+}
+
+/// A term's postings in a range of verses.
+fn in_range<'a>(
+  term: &'a TermPostings,
+  verses: &'a std::ops::Range<usize>,
+) -> impl Iterator<Item = &'a Posting> {
+  let start = term.postings.partition_point(|p| p.verse < verses.start);
+  term.postings[start..]
+    .iter()
+    .take_while(move |p| p.verse < verses.end)
+}
+
+/// The words that appear with `node` in the verses of `range`, or in the whole text when
+/// there is no range, ranked by the chosen measure. Each verse is counted once when there
+/// is no window, and each use of a word near the node when there is one.
+pub fn collocations(
+  postings: &TextPostings,
+  surface_forms: &HashMap<String, String>,
+  node: &TermPostings,
+  range: Option<Bounds>,
+  options: &CollocationOptions,
+) -> Vec<Collocate> {
+  let verses = match range {
+    Some((start, end)) => postings.verse_range(start, end),
+    None => 0..postings.verses.len(),
+  };
+  let node_positions = in_range(node, &verses)
+    .map(|p| (p.verse, &p.positions))
+    .collect::<HashMap<_, _>>();
+  if node_positions.is_empty() {
+    return Vec::new();
+  }
+
+  let (n, r1) = match options.window {
+    Some(window) => {
+      let tokens = postings
+        .terms
+        .iter()
+        .map(|t| t.freq(&verses))
+        .sum::<usize>();
+      let node_freq = node_positions.values().map(|p| p.len()).sum::<usize>();
+      (tokens, node_freq * 2 * window as usize)
+    }
+    None => (verses.len(), node_positions.len()),
+  };
+
+  let mut collocates = postings
+    .terms
+    .iter()
+    .filter(|term| term.term != node.term)
+    .filter_map(|term| {
+      let mut cooccurrences = 0;
+      let mut freq = 0;
+      for posting in in_range(term, &verses) {
+        let node_positions = node_positions.get(&posting.verse);
+        match options.window {
+          Some(window) => {
+            freq += posting.positions.len();
+            if let Some(node_positions) = node_positions {
+              cooccurrences += window_matches(node_positions, &posting.positions, window);
+            }
+          }
+          None => {
+            freq += 1;
+            if node_positions.is_some() {
+              cooccurrences += 1;
+            }
+          }
+        }
+      }
+
+      if cooccurrences < options.min_freq.max(1) {
+        return None;
+      }
+
+      let (o11, r1, c1, n) = (cooccurrences as f64, r1 as f64, freq as f64, n as f64);
+      let pmi = (o11 * n / (r1 * c1)).log2();
+      if pmi <= 0.0 {
+        // The words are seen together no more often than chance.
+        return None;
+      }
+
+      Some(Collocate {
+        term: term.term.clone(),
+        word: surface_forms
+          .get(&term.term)
+          .cloned()
+          .unwrap_or_else(|| term.term.clone()),
+        cooccurrences,
+        freq,
+        pmi,
+        log_likelihood: log_likelihood(o11, r1, c1, n),
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let score = |c: &Collocate| match options.measure {
+    CollocationMeasure::Pmi => c.pmi,
+    CollocationMeasure::LogLikelihood => c.log_likelihood,
+  };
+  collocates.sort_by(|a, b| {
+    score(b)
+      .partial_cmp(&score(a))
+      .unwrap_or(std::cmp::Ordering::Equal)
+  });
+  collocates.truncate(options.limit);
+
+  collocates
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::books::Location;
+  use crate::index::Analyzer;
+
+  #[test]
+  fn window_matches_counts_each_position_once() {
+    assert_eq!(window_matches(&[5], &[3, 4, 6, 8], 2), 3);
+    // Position 2 is near both uses of the node.
+    assert_eq!(window_matches(&[1, 3], &[2], 1), 1);
+    assert_eq!(window_matches(&[1], &[1], 3), 0);
+    assert_eq!(window_matches(&[], &[1, 2], 3), 0);
+  }
+
+  #[test]
+  fn log_likelihood_is_zero_for_independent_words() {
+    assert!(log_likelihood(10.0, 100.0, 100.0, 1000.0).abs() < 1e-9);
+    assert!(log_likelihood(50.0, 100.0, 100.0, 1000.0) > 0.0);
+  }
+
+  #[test]
+  fn window_cooccurrences_stay_within_freq() {
+    let posting = |verse, positions: &[u32]| Posting {
+      verse,
+      positions: positions.to_vec(),
+    };
+    let postings = TextPostings {
+      book_id: String::from("test"),
+      analyzer: Analyzer::Standard,
+      verses: (0..3)
+        .map(|l2| Location {
+          l0: 0,
+          l1: 0,
+          l2: Some(l2),
+        })
+        .collect(),
+      terms: vec![
+        TermPostings {
+          term: String::from("and"),
+          postings: vec![
+            posting(0, &[1, 3, 5]),
+            posting(1, &[1, 3]),
+            posting(2, &[1]),
+          ],
+        },
+        TermPostings {
+          term: String::from("god"),
+          postings: vec![posting(0, &[0, 2, 4]), posting(1, &[0, 2])],
+        },
+        TermPostings {
+          term: String::from("said"),
+          postings: vec![posting(2, &[0, 2, 3, 4, 5, 6, 7])],
+        },
+      ],
+    };
+    let options = CollocationOptions {
+      window: Some(1),
+      min_freq: 1,
+      ..CollocationOptions::default()
+    };
+
+    let collocates = collocations(
+      &postings,
+      &HashMap::new(),
+      &postings.terms[1],
+      None,
+      &options,
+    );
+    let and = collocates.iter().find(|c| c.term == "and").unwrap();
+    assert_eq!(and.cooccurrences, 5);
+    assert_eq!(and.freq, 6);
+    assert!(and.log_likelihood.is_finite() && and.log_likelihood >= 0.0);
+  }
+}
@@ -0,0 +1,215 @@
+use crate::books::Location;
+use crate::corpus::Corpus;
+use crate::postings::{TermPostings, TextPostings};
+use anyhow::anyhow;
+use std::io::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+  Csv,
+  Tsv,
+}
+
+impl FromStr for TableFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    match s {
+      "csv" => Ok(TableFormat::Csv),
+      "tsv" => Ok(TableFormat::Tsv),
+      _ => Err(anyhow!("Unknown table format '{}'", s)),
+    }
+  }
+}
+
+/// Writes rows of a table as CSV, quoting fields where needed, or as TSV, where tabs and
+/// line breaks in a field become spaces.
+pub struct TableWriter<W: Write> {
+  writer: W,
+  format: TableFormat,
+}
+
+impl<W: Write> TableWriter<W> {
+  pub fn new(writer: W, format: TableFormat) -> Self {
+    TableWriter { writer, format }
+  }
+
+  pub fn write_row<S: AsRef<str>>(&mut self, fields: &[S]) -> std::io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+      let field = field.as_ref();
+      match self.format {
+        TableFormat::Csv => {
+          if i > 0 {
+            self.writer.write_all(b",")?;
+          }
+          if field.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(self.writer, "\"{}\"", field.replace('"', "\"\""))?;
+          } else {
+            self.writer.write_all(field.as_bytes())?;
+          }
+        }
+        TableFormat::Tsv => {
+          if i > 0 {
+            self.writer.write_all(b"\t")?;
+          }
+          self
+            .writer
+            .write_all(field.replace(&['\t', '\n', '\r'][..], " ").as_bytes())?;
+        }
+      }
+    }
+    self.writer.write_all(b"\n")
+  }
+
+  pub fn into_inner(self) -> W {
+    self.writer
+  }
+}
+
+/// The level that frequencies are counted at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyLevel {
+  Book,
+  Chapter,
+}
+
+impl FromStr for FrequencyLevel {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    match s {
+      "book" => Ok(FrequencyLevel::Book),
+      "chapter" => Ok(FrequencyLevel::Chapter),
+      _ => Err(anyhow!("Unknown frequency level '{}'", s)),
+    }
+  }
+}
+
+/// The number of times a term appears in a book, or in a chapter when l1 is set.
+#[derive(Debug)]
+pub struct Frequency<'a> {
+  pub term: &'a TermPostings,
+  pub l0: usize,
+  pub l1: Option<usize>,
+  pub freq: usize,
+}
+
+/// Each term's frequency in each book or chapter that it appears in, by term and then by
+/// location.
+pub fn frequencies(postings: &TextPostings, level: FrequencyLevel) -> Vec<Frequency<'_>> {
+  let mut frequencies: Vec<Frequency> = Vec::new();
+  for term in &postings.terms {
+    let first = frequencies.len();
+    for posting in &term.postings {
+      let location = postings.verses[posting.verse];
+      let l1 = match level {
+        FrequencyLevel::Book => None,
+        FrequencyLevel::Chapter => Some(location.l1),
+      };
+
+      match frequencies[first..].last_mut() {
+        Some(f) if f.l0 == location.l0 && f.l1 == l1 => f.freq += posting.positions.len(),
+        _ => frequencies.push(Frequency {
+          term,
+          l0: location.l0,
+          l1,
+          freq: posting.positions.len(),
+        }),
+      }
+    }
+  }
+
+  frequencies
+}
+
+/// One occurrence of a term, with the words around it.
+#[derive(Debug)]
+pub struct KwicLine<'a> {
+  pub term: &'a TermPostings,
+  pub location: Location,
+  pub left: String,
+  /// The word as it appears in the text.
+  pub keyword: &'a str,
+  pub right: String,
+}
+
+/// A keyword-in-context concordance of a text, with a line for every occurrence of each
+/// term that `include` accepts, by term and then by location. The occurrences come from
+/// the index's postings, and the words around them from the text of `corpus`, which must be
+/// the same text. Context is taken from within each occurrence's verse.
+pub fn concordance<'a>(
+  postings: &'a TextPostings,
+  corpus: &'a Corpus,
+  context: usize,
+  include: impl Fn(&str) -> bool,
+) -> Vec<KwicLine<'a>> {
+  let mut lines = Vec::new();
+  for term in postings.terms.iter().filter(|t| include(&t.term)) {
+    for posting in &term.postings {
+      let location = postings.verses[posting.verse];
+      let verse = match corpus
+        .verses
+        .binary_search_by_key(&location.first_verse(), |v| v.location.first_verse())
+      {
+        Ok(i) => &corpus.verses[i],
+        Err(_) => continue,
+      };
+
+      for position in &posting.positions {
+        let (start, end) = match verse.positions.iter().position(|p| p == position) {
+          Some(i) => verse.offsets[i],
+          None => continue,
+        };
+
+        let mut left = verse.text[..start]
+          .split_whitespace()
+          .rev()
+          .take(context)
+          .collect::<Vec<_>>();
+        left.reverse();
+        let right = verse.text[end..]
+          .split_whitespace()
+          .take(context)
+          .collect::<Vec<_>>();
+
+        lines.push(KwicLine {
+          term,
+          location,
+          left: left.join(" "),
+          keyword: &verse.text[start..end],
+          right: right.join(" "),
+        });
+      }
+    }
+  }
+
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn csv_quotes_fields_that_need_it() {
+    let mut table = TableWriter::new(Vec::new(), TableFormat::Csv);
+    table
+      .write_row(&["plain", "a,b", "say \"amen\"", "two\nlines"])
+      .unwrap();
+    assert_eq!(
+      String::from_utf8(table.into_inner()).unwrap(),
+      "plain,\"a,b\",\"say \"\"amen\"\"\",\"two\nlines\"\n"
+    );
+  }
+
+  #[test]
+  fn tsv_replaces_separators() {
+    let mut table = TableWriter::new(Vec::new(), TableFormat::Tsv);
+    table.write_row(&["a\tb", "c\nd", "e,f"]).unwrap();
+    assert_eq!(
+      String::from_utf8(table.into_inner()).unwrap(),
+      "a b\tc d\te,f\n"
+    );
+  }
+}
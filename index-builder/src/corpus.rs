@@ -0,0 +1,180 @@
+use crate::books::Location;
+use crate::error::Error;
+use crate::index::{Analyzer, DocKind, Word};
+use anyhow::anyhow;
+use std::collections::HashMap;
+use tantivy::{
+  collector::TopDocs,
+  query::{BooleanQuery, Occur, Query, TermQuery},
+  schema::IndexRecordOption,
+  Index, Term,
+};
+
+pub struct Verse {
+  pub location: Location,
+  pub text: String,
+  /// The verse's text, run through the analyzer that the text was indexed with.
+  pub tokens: Vec<String>,
+  /// The byte range of the text that each token came from.
+  pub offsets: Vec<(usize, usize)>,
+  /// The position of each token, as recorded in the index. Stop words leave gaps.
+  pub positions: Vec<u32>,
+  /// The lemmas of the verse's tagged words, lowercased as they are indexed.
+  pub lemmas: Vec<String>,
+}
+
+/// The verses of one text, read back from the index in order, for analyses that work on
+/// the whole text at once.
+pub struct Corpus {
+  pub book_id: String,
+  /// The analyzer that the text was indexed with.
+  pub analyzer: Analyzer,
+  pub verses: Vec<Verse>,
+}
+
+impl Corpus {
+  /// Load a text's verses. The tokens come from the text's own analyzer, unless `analyzer`
+  /// is given, so that texts indexed with different analyzers can be compared.
+  pub fn load(index: &Index, book_id: &str, analyzer: Option<Analyzer>) -> Result<Corpus, Error> {
+    let reader = index.reader()?;
+    let searcher = reader.searcher();
+    let schema = index.schema();
+
+    let get_field = |f| {
+      schema
+        .get_field(f)
+        .ok_or_else(|| anyhow!("Failed to find '{}' field in index", f))
+    };
+    let book_field = get_field("book")?;
+    let kind_field = get_field("kind")?;
+    let l0_field = get_field("l0")?;
+    let l1_field = get_field("l1")?;
+    let l2_field = get_field("l2")?;
+    let words_field = get_field("words")?;
+
+    let text_fields = Analyzer::ALL
+      .iter()
+      .map(|a| {
+        let field = get_field(a.field_name())?;
+        let tokenizer_field = get_field(analyzer.unwrap_or(*a).field_name())?;
+        Ok((*a, field, index.tokenizer_for_field(tokenizer_field)?))
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+    let mut text_analyzer = analyzer;
+
+    let term_query = |field, value: &str| -> Box<dyn Query> {
+      Box::new(TermQuery::new(
+        Term::from_field_text(field, value),
+        IndexRecordOption::Basic,
+      ))
+    };
+    let query = BooleanQuery::from(vec![
+      (Occur::Must, term_query(book_field, book_id)),
+      (Occur::Must, term_query(kind_field, DocKind::Verse.as_str())),
+    ]);
+
+    let limit = (searcher.num_docs() as usize).max(1);
+    let mut verses = searcher
+      .search(&query, &TopDocs::with_limit(limit))?
+      .into_iter()
+      .map(|(_score, doc_address)| {
+        let doc = searcher.doc(doc_address)?;
+        let level = |field| {
+          doc
+            .get_first(field)
+            .map(|l| l.u64_value() as usize)
+            .unwrap_or(0)
+        };
+        let location = Location {
+          l0: level(l0_field),
+          l1: level(l1_field),
+          l2: doc.get_first(l2_field).map(|l| l.u64_value() as usize),
+        };
+
+        let (text, tokenizer) = text_fields
+          .iter()
+          .find_map(|(a, field, tokenizer)| {
+            let text = doc.get_first(*field).and_then(|v| v.text())?;
+            text_analyzer.get_or_insert(*a);
+            Some((text, tokenizer))
+          })
+          .unwrap_or(("", &text_fields[0].2));
+
+        let mut tokens = Vec::new();
+        let mut offsets = Vec::new();
+        let mut positions = Vec::new();
+        tokenizer.token_stream(text).process(&mut |token| {
+          tokens.push(token.text.clone());
+          offsets.push((token.offset_from, token.offset_to));
+          positions.push(token.position as u32);
+        });
+
+        let lemmas = match doc.get_first(words_field).and_then(|v| v.text()) {
+          Some(words) => serde_json::from_str::<Vec<Word>>(words)
+            .map_err(anyhow::Error::new)?
+            .into_iter()
+            .flat_map(|word| word.lemma)
+            .map(|lemma| lemma.to_lowercase())
+            .collect(),
+          None => Vec::new(),
+        };
+
+        Ok(Verse {
+          location,
+          text: String::from(text),
+          tokens,
+          offsets,
+          positions,
+          lemmas,
+        })
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    if verses.is_empty() {
+      return Err(Error::Other(anyhow!("No verses found for {}", book_id)));
+    }
+
+    verses.sort_by_key(|v| v.location.first_verse());
+
+    Ok(Corpus {
+      book_id: String::from(book_id),
+      analyzer: text_analyzer.unwrap_or_default(),
+      verses,
+    })
+  }
+
+  /// The most common word in the text for each token, since stemmed tokens such as
+  /// "beginn" aren't fit to show.
+  pub fn surface_forms(&self) -> HashMap<String, String> {
+    let mut counts: HashMap<&str, HashMap<String, usize>> = HashMap::new();
+    for verse in &self.verses {
+      for (token, &(start, end)) in verse.tokens.iter().zip(&verse.offsets) {
+        let word = verse.text.get(start..end).unwrap_or(token).to_lowercase();
+        *counts.entry(token).or_default().entry(word).or_insert(0) += 1;
+      }
+    }
+
+    counts
+      .into_iter()
+      .map(|(token, words)| {
+        let word = words
+          .into_iter()
+          .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+          .map(|(word, _)| word)
+          .unwrap_or_default();
+        (String::from(token), word)
+      })
+      .collect()
+  }
+
+  /// The tokens of the whole text in order, and the index of the verse that each token
+  /// came from.
+  pub fn tokens(&self) -> (Vec<&str>, Vec<usize>) {
+    self
+      .verses
+      .iter()
+      .enumerate()
+      .flat_map(|(i, verse)| verse.tokens.iter().map(move |t| (t.as_str(), i)))
+      .unzip()
+  }
+}
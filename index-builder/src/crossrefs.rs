@@ -0,0 +1,121 @@
+use crate::books::Location;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// A reference from one verse to another verse or range of verses.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrossRef {
+  pub from: Location,
+  pub to: Location,
+  /// The last location, when the reference is to a range.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub to_end: Option<Location>,
+  /// How many people found the reference helpful, for datasets such as OpenBible that
+  /// rank their references.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub votes: Option<i64>,
+}
+
+/// The number of cross references from one chapter to another, for drawing arcs between
+/// chapters.
+#[derive(Serialize, Debug)]
+pub struct ChapterArc {
+  pub from: Location,
+  pub to: Location,
+  pub count: usize,
+}
+
+/// Cross references from each imported dataset, stored in `crossrefs.json` in the data
+/// directory. References are kept sorted by their source verse, in canonical coordinates.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CrossRefs {
+  sources: BTreeMap<String, Vec<CrossRef>>,
+}
+
+impl CrossRefs {
+  pub fn load(dir: &Path) -> Result<CrossRefs> {
+    let path = dir.join("crossrefs.json");
+    match File::open(path) {
+      Ok(f) => Ok(serde_json::from_reader(f)?),
+      Err(_) => Ok(CrossRefs::default()),
+    }
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join("crossrefs.json");
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+
+  /// Replace the references from a dataset.
+  pub fn set(&mut self, source: &str, mut refs: Vec<CrossRef>) {
+    // Within a verse, the most highly voted references come first.
+    refs.sort_by_key(|r| (r.from.first_verse(), std::cmp::Reverse(r.votes)));
+    self.sources.insert(String::from(source), refs);
+  }
+
+  /// The names of the imported datasets.
+  pub fn sources(&self) -> impl Iterator<Item = &str> {
+    self.sources.keys().map(|s| s.as_str())
+  }
+
+  /// The references from a dataset whose source verse is between the (l0, l1, l2)
+  /// coordinates `start` and `end`, inclusive.
+  pub fn range(
+    &self,
+    source: &str,
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+  ) -> &[CrossRef] {
+    let refs = match self.sources.get(source) {
+      Some(refs) => refs.as_slice(),
+      None => return &[],
+    };
+
+    let first = refs.partition_point(|r| r.from.first_verse() < start);
+    let last = refs.partition_point(|r| r.from.first_verse() <= end);
+    &refs[first..last.max(first)]
+  }
+
+  /// Count the references between each pair of chapters, from the datasets in `sources`
+  /// whose source verse is between `start` and `end`. A reference to a range counts
+  /// toward the chapter where the range starts. References with fewer than `min_votes`
+  /// votes are skipped, when the dataset has votes.
+  pub fn chapter_arcs(
+    &self,
+    sources: &[&str],
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+    min_votes: Option<i64>,
+  ) -> Vec<ChapterArc> {
+    // Keyed by the l0 and l1 of each end.
+    let mut counts: BTreeMap<(usize, usize, usize, usize), usize> = BTreeMap::new();
+    for source in sources {
+      for r in self.range(source, start, end) {
+        if let (Some(min_votes), Some(votes)) = (min_votes, r.votes) {
+          if votes < min_votes {
+            continue;
+          }
+        }
+
+        let key = (r.from.l0, r.from.l1, r.to.l0, r.to.l1);
+        *counts.entry(key).or_insert(0) += 1;
+      }
+    }
+
+    let chapter = |l0, l1| Location { l0, l1, l2: None };
+    counts
+      .into_iter()
+      .map(|((from_l0, from_l1, to_l0, to_l1), count)| ChapterArc {
+        from: chapter(from_l0, from_l1),
+        to: chapter(to_l0, to_l1),
+        count,
+      })
+      .collect()
+  }
+}
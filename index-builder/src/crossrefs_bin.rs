@@ -0,0 +1,171 @@
+use anyhow::{anyhow, Context, Result};
+use quick_xml::events::Event;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::StructOpt;
+use study_map_index::books::{parse_osis_ref, Location};
+use study_map_index::crossrefs::{CrossRef, CrossRefs};
+
+#[derive(Debug)]
+enum Format {
+  OpenBible,
+  Osis,
+}
+
+impl FromStr for Format {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Format> {
+    match s {
+      "openbible" => Ok(Format::OpenBible),
+      "osis" => Ok(Format::Osis),
+      _ => Err(anyhow!("Unknown format {}", s)),
+    }
+  }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "crossref-indexer")]
+pub struct Config {
+  #[structopt(short, long, parse(from_os_str))]
+  file: PathBuf,
+
+  #[structopt(
+    long,
+    default_value = "openbible",
+    help = r#""openbible" for the OpenBible.info tab-separated cross references, or "osis"
+      for <reference> elements in an OSIS file, such as the Treasury of Scripture Knowledge"#
+  )]
+  format: Format,
+
+  #[structopt(
+    short,
+    long,
+    help = r#"The name of the dataset. Defaults to "openbible" or "tsk", depending on the
+      format. Importing a dataset replaces any references previously imported under its name"#
+  )]
+  name: Option<String>,
+
+  #[structopt(short, long, help = r#"Defaults to ./api/data"#)]
+  output: Option<PathBuf>,
+}
+
+fn cross_ref(from: Location, to: &str, votes: Option<i64>) -> Option<CrossRef> {
+  let (to, to_end) = parse_osis_ref(to)?;
+  Some(CrossRef {
+    from,
+    to,
+    to_end: if to_end == to { None } else { Some(to_end) },
+    votes,
+  })
+}
+
+/// Read the OpenBible.info format, which has a header line and then lines of
+/// "From Verse", "To Verse", and "Votes", e.g. "Gen.1.1", "Prov.8.22-Prov.8.30", "59".
+fn read_openbible(path: &Path) -> Result<Vec<CrossRef>> {
+  let f = std::fs::File::open(path).with_context(|| format!("Failed to open file {:?}", path))?;
+
+  let mut refs = Vec::new();
+  for line in BufReader::new(f).lines() {
+    let line = line?;
+    let mut columns = line.split('\t');
+    let (from, to) = match (columns.next(), columns.next()) {
+      (Some(from), Some(to)) => (from, to),
+      _ => continue,
+    };
+    let votes = columns.next().and_then(|v| v.trim().parse::<i64>().ok());
+
+    // This also skips the header line.
+    let from = match parse_osis_ref(from) {
+      Some((from, _)) => from,
+      None => continue,
+    };
+
+    refs.extend(cross_ref(from, to, votes));
+  }
+
+  Ok(refs)
+}
+
+/// Read `<reference osisRef="...">` elements from an OSIS file. Each reference is from the
+/// verse that contains it, or from the `annotateRef` of the commentary section that contains
+/// it, as in the Treasury of Scripture Knowledge.
+fn read_osis(path: &Path) -> Result<Vec<CrossRef>> {
+  let mut reader = quick_xml::Reader::from_file(path)
+    .with_context(|| format!("Failed to open file {:?}", path))?;
+
+  let get_attr = |e: &quick_xml::events::BytesStart, key: &[u8]| {
+    e.attributes()
+      .filter_map(|a| a.ok())
+      .find(|a| a.key == key)
+      .map(|a| String::from_utf8_lossy(&a.unescaped_value().unwrap_or_default()).to_string())
+  };
+
+  let mut refs = Vec::new();
+  let mut from: Option<Location> = None;
+  let mut buf = Vec::new();
+  loop {
+    match reader.read_event(&mut buf)? {
+      Event::Start(ref e) | Event::Empty(ref e) => {
+        let source = match e.name() {
+          b"verse" => get_attr(e, b"osisID").or_else(|| get_attr(e, b"sID")),
+          _ => get_attr(e, b"annotateRef"),
+        };
+        // A verse may have several IDs when verses are combined, and the first is used.
+        if let Some(source) = source {
+          from = source
+            .split_whitespace()
+            .next()
+            .and_then(parse_osis_ref)
+            .map(|(start, _)| start);
+        }
+
+        if e.name() == b"reference" {
+          let to = get_attr(e, b"osisRef");
+          if let (Some(from), Some(to)) = (from, to) {
+            refs.extend(cross_ref(from, &to, None));
+          }
+        }
+      }
+      Event::Eof => break,
+      _ => (),
+    }
+    buf.clear();
+  }
+
+  Ok(refs)
+}
+
+fn main() -> Result<()> {
+  let Config {
+    file,
+    format,
+    name,
+    output,
+  } = Config::from_args();
+
+  let refs = match format {
+    Format::OpenBible => read_openbible(&file)?,
+    Format::Osis => read_osis(&file)?,
+  };
+
+  if refs.is_empty() {
+    return Err(anyhow!("No cross references found in {}", file.display()));
+  }
+
+  let name = name.unwrap_or_else(|| {
+    String::from(match format {
+      Format::OpenBible => "openbible",
+      Format::Osis => "tsk",
+    })
+  });
+
+  let data_path = output.unwrap_or_else(|| std::env::current_dir().unwrap().join("api/data"));
+  let mut crossrefs = CrossRefs::load(&data_path)?;
+  println!("Imported {} cross references as {}", refs.len(), name);
+  crossrefs.set(&name, refs);
+  crossrefs.write(&data_path)?;
+
+  Ok(())
+}
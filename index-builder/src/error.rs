@@ -5,6 +5,9 @@ pub enum Error {
   #[error("Invalid query")]
   QueryParseError,
 
+  #[error("Not found")]
+  NotFound,
+
   #[error("Search Error: {0}")]
   TantivyError(tantivy::TantivyError),
 
@@ -1,32 +1,59 @@
 use crate::readonly_dir_wrapper;
-use anyhow::Result;
+use crate::tokenizers::{ArchaicNormalizer, CjkTokenizer, StrongNormalizer, MODERNIZED_PRONOUNS};
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::fs::File;
 use std::path::Path;
+use std::str::FromStr;
 use tantivy::{
   directory::MmapDirectory,
   schema::{IndexRecordOption, Schema, TextFieldIndexing, TextOptions, INDEXED, STORED},
   tokenizer::{
-    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer,
+    Language, LowerCaser, RawTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter,
+    TextAnalyzer,
   },
 };
 
 pub fn open_readonly_index(dir: &Path) -> Result<tantivy::Index, tantivy::TantivyError> {
   let mmap_directory = MmapDirectory::open(dir)?;
   let d = readonly_dir_wrapper::ReadOnlyDirectoryWrapper::new(mmap_directory);
-  tantivy::Index::open(d).map(|mut index| {
-    make_book_tokenizer(&mut index);
-    index
-  })
+  let mut index = tantivy::Index::open(d)?;
+  check_schema(&index)?;
+  register_tokenizers(&mut index);
+  Ok(index)
 }
 
 pub fn open_index(dir: &Path) -> Result<tantivy::Index, tantivy::TantivyError> {
   if let Ok(mut index_result) = tantivy::Index::open_in_dir(dir) {
-    make_book_tokenizer(&mut index_result);
+    check_schema(&index_result)?;
+    register_tokenizers(&mut index_result);
     return Ok(index_result);
   }
 
+  let mut index = tantivy::Index::create_in_dir(dir, build_schema())?;
+  register_tokenizers(&mut index);
+
+  Ok(index)
+}
+
+/// Make sure that an existing index has every field that the current schema does. Fields
+/// can't be added to an index, so one built by an older version has to be rebuilt.
+fn check_schema(index: &tantivy::Index) -> Result<(), tantivy::TantivyError> {
+  let existing = index.schema();
+  match build_schema()
+    .fields()
+    .find(|(_, entry)| existing.get_field(entry.name()).is_none())
+  {
+    Some((_, entry)) => Err(tantivy::TantivyError::SchemaError(format!(
+      "The index has no '{}' field, so it was built by an older version. Delete the index and rebuild it.",
+      entry.name()
+    ))),
+    None => Ok(()),
+  }
+}
+
+fn build_schema() -> Schema {
   let mut schema = Schema::builder();
   schema.add_text_field(
     "doc_id",
@@ -46,28 +73,222 @@ pub fn open_index(dir: &Path) -> Result<tantivy::Index, tantivy::TantivyError> {
     ),
   );
 
+  schema.add_text_field(
+    "kind",
+    TextOptions::default().set_stored().set_indexing_options(
+      TextFieldIndexing::default()
+        .set_index_option(IndexRecordOption::Basic)
+        .set_tokenizer("raw"),
+    ),
+  );
+
   schema.add_u64_field("l0", INDEXED | STORED);
   schema.add_u64_field("l1", INDEXED | STORED);
   schema.add_u64_field("l2", INDEXED | STORED);
+  // The last verse covered by a pericope.
+  schema.add_u64_field("end_l1", STORED);
+  schema.add_u64_field("end_l2", STORED);
+  // Start and end offsets of the text that is words of Christ, as a flat list of pairs.
+  schema.add_u64_field("red_letter", STORED);
+
+  for analyzer in Analyzer::ALL.iter() {
+    let text_options = TextOptions::default().set_stored().set_indexing_options(
+      TextFieldIndexing::default()
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+        .set_tokenizer(analyzer.tokenizer_name()),
+    );
 
-  let text_options = TextOptions::default().set_stored().set_indexing_options(
-    TextFieldIndexing::default()
-      .set_index_option(IndexRecordOption::WithFreqsAndPositions)
-      .set_tokenizer("book_tokenizer"),
+    schema.add_text_field(analyzer.field_name(), text_options.clone());
+    schema.add_text_field(analyzer.heading_field_name(), text_options);
+
+    // The words of Christ and the rest of the text, indexed separately so that a search can
+    // be limited to one or the other.
+    let split_options = TextOptions::default().set_indexing_options(
+      TextFieldIndexing::default()
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+        .set_tokenizer(analyzer.tokenizer_name()),
+    );
+    schema.add_text_field(analyzer.red_letter_field_name(true), split_options.clone());
+    schema.add_text_field(analyzer.red_letter_field_name(false), split_options);
+  }
+
+  // Original language tags on the words of a verse, indexed from pre-tokenized text.
+  // Tantivy doesn't store the tokens, so the tagged words are stored separately.
+  for (field, tokenizer) in &[("strong", "strong_tokenizer"), ("lemma", "lemma_tokenizer")] {
+    let tag_options = TextOptions::default().set_indexing_options(
+      TextFieldIndexing::default()
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+        .set_tokenizer(tokenizer),
+    );
+    schema.add_text_field(field, tag_options);
+  }
+
+  // Morphology features, with all of a word's features and tags at the same position.
+  schema.add_text_field(
+    "morph",
+    TextOptions::default().set_indexing_options(
+      TextFieldIndexing::default()
+        .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+        .set_tokenizer("raw"),
+    ),
   );
+  schema.add_text_field("words", STORED);
 
-  schema.add_text_field("text", text_options);
+  schema.build()
+}
 
-  let mut index = tantivy::Index::create_in_dir(dir, schema.build())?;
-  make_book_tokenizer(&mut index);
+/// What a document in the index represents.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocKind {
+  /// A single verse.
+  Verse,
+  /// The text of all the verses in a chapter.
+  Chapter,
+  /// The text of all the verses under a section heading, which may cross chapters.
+  Pericope,
+  /// A footnote or translator's note, at the location of the verse it belongs to.
+  Note,
+}
 
-  Ok(index)
+impl DocKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DocKind::Verse => "verse",
+      DocKind::Chapter => "chapter",
+      DocKind::Pericope => "pericope",
+      DocKind::Note => "note",
+    }
+  }
+}
+
+impl FromStr for DocKind {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s {
+      "verse" => Ok(DocKind::Verse),
+      "chapter" => Ok(DocKind::Chapter),
+      "pericope" => Ok(DocKind::Pericope),
+      "note" => Ok(DocKind::Note),
+      _ => Err(anyhow!("Unknown document kind '{}'", s)),
+    }
+  }
+}
+
+/// A word tagged with original language information, as in `<w lemma="strong:G26">`. The
+/// tagged words of each document are stored as JSON in the "words" field.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Word {
+  /// The byte range of the word in the document's text.
+  pub start: usize,
+  pub end: usize,
+  /// Strong's numbers, e.g. "G26".
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub strong: Vec<String>,
+  /// Dictionary forms of the original word.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub lemma: Vec<String>,
+  /// Morphology codes, e.g. "V-AAI-3S".
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub morph: Vec<String>,
+  /// A transliteration of the original word.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub xlit: Option<String>,
+}
+
+impl Word {
+  /// A word with no tags.
+  pub fn untagged(start: usize, end: usize) -> Word {
+    Word {
+      start,
+      end,
+      strong: Vec::new(),
+      lemma: Vec::new(),
+      morph: Vec::new(),
+      xlit: None,
+    }
+  }
+
+  pub fn is_tagged(&self) -> bool {
+    !self.strong.is_empty() || !self.lemma.is_empty() || !self.morph.is_empty()
+  }
+}
+
+/// The analysis chain used to index a text. Since a field can only have one tokenizer,
+/// each analyzer writes its verses into a separate text field.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Analyzer {
+  /// English text, with stopword removal and stemming.
+  #[default]
+  Standard,
+  /// Chinese, Japanese, and Korean text, split into character bigrams.
+  Cjk,
+  /// Early Modern English, such as the KJV, normalized to modern forms before stemming.
+  Archaic,
+}
+
+impl Analyzer {
+  pub const ALL: [Analyzer; 3] = [Analyzer::Standard, Analyzer::Cjk, Analyzer::Archaic];
+
+  pub fn field_name(&self) -> &'static str {
+    match self {
+      Analyzer::Standard => "text",
+      Analyzer::Cjk => "text_cjk",
+      Analyzer::Archaic => "text_archaic",
+    }
+  }
+
+  /// The field for section headings, which are kept out of the text field.
+  pub fn heading_field_name(&self) -> &'static str {
+    match self {
+      Analyzer::Standard => "heading",
+      Analyzer::Cjk => "heading_cjk",
+      Analyzer::Archaic => "heading_archaic",
+    }
+  }
+
+  /// The field for only the red letter text, or for only the rest of the text.
+  pub fn red_letter_field_name(&self, red: bool) -> &'static str {
+    match (self, red) {
+      (Analyzer::Standard, true) => "text_red",
+      (Analyzer::Standard, false) => "text_black",
+      (Analyzer::Cjk, true) => "text_cjk_red",
+      (Analyzer::Cjk, false) => "text_cjk_black",
+      (Analyzer::Archaic, true) => "text_archaic_red",
+      (Analyzer::Archaic, false) => "text_archaic_black",
+    }
+  }
+
+  fn tokenizer_name(&self) -> &'static str {
+    match self {
+      Analyzer::Standard => "book_tokenizer",
+      Analyzer::Cjk => "cjk_tokenizer",
+      Analyzer::Archaic => "archaic_tokenizer",
+    }
+  }
+}
+
+impl FromStr for Analyzer {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s {
+      "standard" => Ok(Analyzer::Standard),
+      "cjk" => Ok(Analyzer::Cjk),
+      "archaic" => Ok(Analyzer::Archaic),
+      _ => Err(anyhow!("Unknown analyzer '{}'", s)),
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct CatalogItem {
   pub id: String,
   pub name: String,
+  #[serde(default)]
+  pub analyzer: Analyzer,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -102,9 +323,61 @@ impl Catalog {
   }
 }
 
-fn make_book_tokenizer(index: &mut tantivy::Index) {
+fn register_tokenizers(index: &mut tantivy::Index) {
+  let stopwords = english_stopwords();
+
+  let book_tokenizer = TextAnalyzer::from(SimpleTokenizer)
+    .filter(RemoveLongFilter::limit(40))
+    .filter(LowerCaser)
+    .filter(StopWordFilter::remove(stopwords.clone()))
+    .filter(Stemmer::new(Language::English));
+  index
+    .tokenizers()
+    .register("book_tokenizer", book_tokenizer);
+
+  // The archaic pronouns are normalized to modern ones that are stopwords, so keep those
+  // to let "thee" and "thou" still be searched for.
+  let archaic_stopwords = stopwords
+    .into_iter()
+    .filter(|word| !MODERNIZED_PRONOUNS.contains(&word.as_str()))
+    .collect();
+  let archaic_tokenizer = TextAnalyzer::from(SimpleTokenizer)
+    .filter(RemoveLongFilter::limit(40))
+    .filter(LowerCaser)
+    .filter(ArchaicNormalizer)
+    .filter(StopWordFilter::remove(archaic_stopwords))
+    .filter(Stemmer::new(Language::English));
+  index
+    .tokenizers()
+    .register("archaic_tokenizer", archaic_tokenizer);
+
+  let cjk_tokenizer = TextAnalyzer::from(CjkTokenizer)
+    .filter(RemoveLongFilter::limit(40))
+    .filter(LowerCaser);
+  index.tokenizers().register("cjk_tokenizer", cjk_tokenizer);
+
+  let strong_tokenizer = TextAnalyzer::from(RawTokenizer).filter(StrongNormalizer);
+  index
+    .tokenizers()
+    .register("strong_tokenizer", strong_tokenizer);
+
+  let lemma_tokenizer = TextAnalyzer::from(RawTokenizer).filter(LowerCaser);
+  index
+    .tokenizers()
+    .register("lemma_tokenizer", lemma_tokenizer);
+
+  // Words as written, for counting a text's vocabulary rather than searching it.
+  let word_tokenizer = TextAnalyzer::from(SimpleTokenizer)
+    .filter(RemoveLongFilter::limit(40))
+    .filter(LowerCaser);
+  index
+    .tokenizers()
+    .register("word_tokenizer", word_tokenizer);
+}
+
+fn english_stopwords() -> Vec<String> {
   // This is the stopword list used by Lucene, taken from https://snowballstem.org/algorithms/english/stop.txt
-  let stopwords = vec![
+  vec![
     "i",
     "me",
     "my",
@@ -282,13 +555,5 @@ fn make_book_tokenizer(index: &mut tantivy::Index) {
   ]
   .into_iter()
   .map(|x| String::from(x))
-  .collect::<Vec<_>>();
-
-  let tokenizer = TextAnalyzer::from(SimpleTokenizer)
-    .filter(RemoveLongFilter::limit(40))
-    .filter(LowerCaser)
-    .filter(StopWordFilter::remove(stopwords))
-    .filter(Stemmer::new(Language::English));
-
-  index.tokenizers().register("book_tokenizer", tokenizer);
+  .collect::<Vec<_>>()
 }
@@ -0,0 +1,171 @@
+use crate::books::Bounds;
+use crate::postings::TextPostings;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// How to score a word's over-representation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeynessMeasure {
+  /// Dunning's log-likelihood ratio, which is reliable for rare words.
+  LogLikelihood,
+  /// Pearson's chi-square test on the 2x2 table of counts.
+  ChiSquare,
+}
+
+impl FromStr for KeynessMeasure {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> anyhow::Result<Self> {
+    match s {
+      "log_likelihood" => Ok(KeynessMeasure::LogLikelihood),
+      "chi_square" => Ok(KeynessMeasure::ChiSquare),
+      _ => Err(anyhow!("Unknown keyness measure '{}'", s)),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeynessOptions {
+  /// The measure to rank words by.
+  pub measure: KeynessMeasure,
+  pub limit: usize,
+  /// Words that appear fewer times than this in the target are left out, since a rare
+  /// word can look distinctive by chance.
+  pub min_freq: usize,
+}
+
+impl Default for KeynessOptions {
+  fn default() -> Self {
+    KeynessOptions {
+      measure: KeynessMeasure::LogLikelihood,
+      limit: 50,
+      min_freq: 3,
+    }
+  }
+}
+
+/// A word that appears more often in the target than in the reference.
+#[derive(Serialize, Debug)]
+pub struct KeyWord {
+  /// The indexed term.
+  pub term: String,
+  /// The most common form of the term in the text.
+  pub word: String,
+  /// The number of times the word appears in the target.
+  pub freq: usize,
+  /// The number of times the word appears in the reference.
+  pub reference_freq: usize,
+  pub log_likelihood: f64,
+  pub chi_square: f64,
+}
+
+fn log_likelihood(a: f64, b: f64, c: f64, d: f64) -> f64 {
+  let e1 = c * (a + b) / (c + d);
+  let e2 = d * (a + b) / (c + d);
+  let term = |o: f64, e: f64| if o > 0.0 { o * (o / e).ln() } else { 0.0 };
+  2.0 * (term(a, e1) + term(b, e2))
+}
+
+fn chi_square(a: f64, b: f64, c: f64, d: f64) -> f64 {
+  let n = c + d;
+  let denominator = c * d * (a + b) * (n - a - b);
+  if denominator > 0.0 {
+    n * (a * (d - b) - b * (c - a)).powi(2) / denominator
+  } else {
+    0.0
+  }
+}
+
+/// The words most over-represented in the `target` verses of a text, compared to the
+/// `reference` verses, or to the rest of the text when there is no reference. Words are
+/// ranked by the chosen measure, highest first.
+pub fn keyness(
+  postings: &TextPostings,
+  surface_forms: &HashMap<String, String>,
+  target: Bounds,
+  reference: Option<Bounds>,
+  options: &KeynessOptions,
+) -> Vec<KeyWord> {
+  let target = postings.verse_range(target.0, target.1);
+  let reference = reference.map(|(start, end)| postings.verse_range(start, end));
+
+  let counts = postings
+    .terms
+    .iter()
+    .map(|term| {
+      let freq = term.freq(&target);
+      let reference_freq = match &reference {
+        Some(reference) => term.freq(reference),
+        None => term.freq(&(0..postings.verses.len())) - freq,
+      };
+      (term, freq, reference_freq)
+    })
+    .collect::<Vec<_>>();
+
+  let target_total = counts.iter().map(|(_, freq, _)| freq).sum::<usize>() as f64;
+  let reference_total = counts.iter().map(|(_, _, freq)| freq).sum::<usize>() as f64;
+  if target_total == 0.0 || reference_total == 0.0 {
+    return Vec::new();
+  }
+
+  let mut words = counts
+    .into_iter()
+    .filter(|&(_, freq, reference_freq)| {
+      freq >= options.min_freq.max(1)
+        && freq as f64 / target_total > reference_freq as f64 / reference_total
+    })
+    .map(|(term, freq, reference_freq)| {
+      let (a, b) = (freq as f64, reference_freq as f64);
+      KeyWord {
+        term: term.term.clone(),
+        word: surface_forms
+          .get(&term.term)
+          .cloned()
+          .unwrap_or_else(|| term.term.clone()),
+        freq,
+        reference_freq,
+        log_likelihood: log_likelihood(a, b, target_total, reference_total),
+        chi_square: chi_square(a, b, target_total, reference_total),
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let score = |w: &KeyWord| match options.measure {
+    KeynessMeasure::LogLikelihood => w.log_likelihood,
+    KeynessMeasure::ChiSquare => w.chi_square,
+  };
+  words.sort_by(|a, b| {
+    score(b)
+      .partial_cmp(&score(a))
+      .unwrap_or(std::cmp::Ordering::Equal)
+  });
+  words.truncate(options.limit);
+
+  words
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn log_likelihood_scores_differences() {
+    // A word used at the same rate in both corpora isn't key.
+    assert!(log_likelihood(10.0, 100.0, 1000.0, 10000.0).abs() < 1e-9);
+    // A word ten times as frequent in the target.
+    assert!((log_likelihood(10.0, 10.0, 1000.0, 10000.0) - 22.1382).abs() < 1e-3);
+    // A word missing from the reference only contributes the target's term.
+    assert!(log_likelihood(5.0, 0.0, 100.0, 100.0) > 0.0);
+  }
+
+  #[test]
+  fn chi_square_scores_differences() {
+    assert!(chi_square(10.0, 100.0, 1000.0, 10000.0).abs() < 1e-9);
+    assert!((chi_square(10.0, 10.0, 1000.0, 10000.0) - 40.5738).abs() < 1e-3);
+    // An empty corpus has nothing to compare.
+    assert_eq!(chi_square(0.0, 0.0, 0.0, 100.0), 0.0);
+  }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// A dictionary entry for an original language word, keyed by its Strong's number.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LexiconEntry {
+  /// The Strong's number, e.g. "G26".
+  pub id: String,
+  /// The word in its original script.
+  pub lemma: String,
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub xlit: String,
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub pronunciation: String,
+  /// A short summary of the ways the word is translated.
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub gloss: String,
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  pub definition: String,
+}
+
+impl LexiconEntry {
+  /// A query that finds the verses containing this word, either by its Strong's number or
+  /// by its lemma.
+  pub fn query(&self) -> String {
+    if self.lemma.is_empty() {
+      format!("strong:{}", self.id)
+    } else {
+      format!("strong:{} OR lemma:\"{}\"", self.id, self.lemma)
+    }
+  }
+}
+
+/// The dictionaries that have been imported, stored in `lexicon.json` in the data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Lexicon {
+  entries: BTreeMap<String, LexiconEntry>,
+}
+
+impl Lexicon {
+  pub fn load(dir: &Path) -> Result<Lexicon> {
+    let path = dir.join("lexicon.json");
+    match File::open(path) {
+      Ok(f) => Ok(serde_json::from_reader(f)?),
+      Err(_) => Ok(Lexicon::default()),
+    }
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join("lexicon.json");
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+
+  /// Add an entry, replacing any existing entry with the same Strong's number.
+  pub fn add(&mut self, entry: LexiconEntry) {
+    self.entries.insert(entry.id.clone(), entry);
+  }
+
+  pub fn get(&self, id: &str) -> Option<&LexiconEntry> {
+    self.entries.get(id)
+  }
+}
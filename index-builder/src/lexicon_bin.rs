@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use study_map_index::lexicon::{Lexicon, LexiconEntry};
+use study_map_index::normalize_strong;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "lexicon-indexer")]
+pub struct Config {
+  #[structopt(
+    short,
+    long,
+    parse(from_os_str),
+    help = r#"A Strong's dictionary in the Open Scriptures XML format, either the Greek
+      <entry> format or the Hebrew OSIS format"#
+  )]
+  file: PathBuf,
+
+  #[structopt(short, long, help = r#"Defaults to ./api/data"#)]
+  output: Option<PathBuf>,
+}
+
+fn get_attr(e: &BytesStart, key: &[u8]) -> Option<String> {
+  e.attributes()
+    .filter_map(|a| a.ok())
+    .find(|a| a.key == key)
+    .map(|a| String::from_utf8_lossy(&a.unescaped_value().unwrap_or_default()).to_string())
+}
+
+/// The Strong's number for a dictionary's numbering, e.g. "G26" for "00026" in the Greek
+/// dictionary.
+fn strong_id(prefix: &str, number: &str) -> String {
+  let id = format!("{}{}", prefix, number);
+  normalize_strong(&id).unwrap_or(id)
+}
+
+/// Which part of the entry the current text belongs to.
+#[derive(Clone, Copy, PartialEq)]
+enum Part {
+  Definition,
+  Gloss,
+}
+
+fn part_text(entry: &mut LexiconEntry, part: Part) -> &mut String {
+  match part {
+    Part::Definition => &mut entry.definition,
+    Part::Gloss => &mut entry.gloss,
+  }
+}
+
+fn new_entry(id: String) -> LexiconEntry {
+  LexiconEntry {
+    id,
+    lemma: String::new(),
+    xlit: String::new(),
+    pronunciation: String::new(),
+    gloss: String::new(),
+    definition: String::new(),
+  }
+}
+
+/// Tidy up whitespace, and remove the ":--" that starts the KJV usage in the Greek dictionary.
+fn clean(text: &str) -> String {
+  let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+  String::from(text.trim_start_matches(":--").trim())
+}
+
+fn read<F: FnMut(LexiconEntry)>(path: &Path, mut callback: F) -> Result<()> {
+  let mut reader = quick_xml::Reader::from_file(path)
+    .with_context(|| format!("Failed to open file {:?}", path))?;
+
+  // The Greek dictionary numbers its entries without a language prefix, and names the
+  // language in a child element.
+  let mut number: Option<String> = None;
+  let mut entry: Option<LexiconEntry> = None;
+  let mut part: Option<Part> = None;
+
+  let mut buf = Vec::new();
+  loop {
+    let event = reader.read_event(&mut buf)?;
+    match event {
+      Event::Start(ref e) | Event::Empty(ref e) => {
+        let empty = matches!(event, Event::Empty(_));
+        match e.name() {
+          b"entry" => number = get_attr(e, b"strongs"),
+          b"greek" | b"hebrew" if entry.is_none() => {
+            let prefix = if e.name() == b"greek" { "G" } else { "H" };
+            entry = number.as_deref().map(|n| new_entry(strong_id(prefix, n)));
+            if let Some(entry) = entry.as_mut() {
+              entry.lemma = get_attr(e, b"unicode").unwrap_or_default();
+              entry.xlit = get_attr(e, b"translit").unwrap_or_default();
+            }
+          }
+          // Hebrew entries are OSIS divs that start with the word itself, with its pronunciation
+          // in the POS attribute.
+          b"w" if entry.is_none() => {
+            entry = get_attr(e, b"ID").map(|id| new_entry(strong_id("", &id)));
+            if let Some(entry) = entry.as_mut() {
+              entry.lemma = get_attr(e, b"lemma").unwrap_or_default();
+              entry.xlit = get_attr(e, b"xlit").unwrap_or_default();
+              entry.pronunciation = get_attr(e, b"POS").unwrap_or_default();
+            }
+          }
+          b"pronunciation" => {
+            if let Some(entry) = entry.as_mut() {
+              entry.pronunciation = get_attr(e, b"strongs").unwrap_or_default();
+            }
+          }
+          b"strongs_def" | b"kjv_def" | b"note" if !empty => {
+            part = match (e.name(), get_attr(e, b"type").as_deref()) {
+              (b"strongs_def", _) => Some(Part::Definition),
+              (b"kjv_def", _) => Some(Part::Gloss),
+              (b"note", Some("exegesis")) | (b"note", Some("explanation")) => {
+                Some(Part::Definition)
+              }
+              (b"note", Some("translation")) => Some(Part::Gloss),
+              _ => None,
+            };
+            // Separate the text of several notes.
+            if let (Some(entry), Some(part)) = (entry.as_mut(), part) {
+              part_text(entry, part).push(' ');
+            }
+          }
+          // References to other entries are written as empty elements in the Greek
+          // dictionary.
+          b"strongsref" => {
+            let prefix = match get_attr(e, b"language").as_deref() {
+              Some("HEBREW") => "H",
+              _ => "G",
+            };
+            let reference = get_attr(e, b"strongs").map(|n| strong_id(prefix, &n));
+            if let (Some(entry), Some(part), Some(reference)) = (entry.as_mut(), part, reference) {
+              part_text(entry, part).push_str(&reference);
+            }
+          }
+          _ => (),
+        }
+      }
+      Event::Text(ref t) => {
+        if let (Some(entry), Some(part)) = (entry.as_mut(), part) {
+          part_text(entry, part).push_str(&t.unescape_and_decode(&reader)?);
+        }
+      }
+      Event::End(ref e) => match e.name() {
+        b"strongs_def" | b"kjv_def" | b"note" => part = None,
+        b"entry" | b"div" => {
+          if let Some(mut entry) = entry.take() {
+            entry.definition = clean(&entry.definition);
+            entry.gloss = clean(&entry.gloss);
+            callback(entry);
+          }
+          number = None;
+          part = None;
+        }
+        _ => (),
+      },
+      Event::Eof => break,
+      _ => (),
+    }
+    buf.clear();
+  }
+
+  Ok(())
+}
+
+fn main() -> Result<()> {
+  let Config { file, output } = Config::from_args();
+
+  let data_path = output.unwrap_or_else(|| std::env::current_dir().unwrap().join("api/data"));
+  let mut lexicon = Lexicon::load(&data_path)?;
+
+  let mut count = 0;
+  read(&file, |entry| {
+    lexicon.add(entry);
+    count += 1;
+  })?;
+
+  if count == 0 {
+    return Err(anyhow!("No dictionary entries found in {}", file.display()));
+  }
+
+  lexicon.write(&data_path)?;
+  println!("Imported {} entries", count);
+
+  Ok(())
+}
@@ -1,7 +1,27 @@
 mod error;
+pub mod morphology;
+mod query;
 mod readonly_dir_wrapper;
+mod rng;
+mod tokenizers;
 
+pub mod books;
+pub mod collocations;
+pub mod concordance;
+pub mod corpus;
+pub mod crossrefs;
 pub mod index;
+pub mod keyness;
+pub mod lexicon;
+pub mod parallels;
+pub mod postings;
+pub mod quotations;
 pub mod search;
+pub mod shingles;
 pub mod stats;
+pub mod synonyms;
+pub mod topics;
+pub mod vectors;
+pub mod vocabulary;
 pub use error::Error;
+pub use tokenizers::normalize_strong;
@@ -0,0 +1,304 @@
+use crate::index::Word;
+use crate::tokenizers::normalize_strong;
+
+/// The features that can be searched in the morphology field, as `feature:value`. `morph`
+/// matches the whole tag, and `lemma` and `strong` can be combined with the others to
+/// find a particular word with a particular form.
+pub const FEATURES: &[&str] = &[
+  "case", "gender", "mood", "morph", "number", "person", "pos", "tense", "voice",
+];
+
+/// Return the morphology field tokens for a word. Each token is a `feature:value` pair, and
+/// all of a word's tokens are indexed at the same position so that a query can require
+/// several of them on one word.
+pub fn word_tokens(word: &Word) -> Vec<String> {
+  let mut tokens = Vec::new();
+  if word.morph.is_empty() {
+    return tokens;
+  }
+
+  for code in &word.morph {
+    tokens.extend(features(code));
+  }
+
+  tokens.extend(
+    word
+      .lemma
+      .iter()
+      .map(|l| format!("lemma:{}", l.to_lowercase())),
+  );
+  tokens.extend(word.strong.iter().map(|n| {
+    format!(
+      "strong:{}",
+      normalize_strong(n).unwrap_or_else(|| n.clone())
+    )
+  }));
+
+  tokens
+}
+
+/// The token for a feature in a query, normalized the same way as indexed tokens.
+pub fn query_token(feature: &str, value: &str) -> String {
+  match feature {
+    "morph" => format!("morph:{}", value.to_uppercase()),
+    "strong" => format!(
+      "strong:{}",
+      normalize_strong(value).unwrap_or_else(|| String::from(value))
+    ),
+    _ => format!("{}:{}", feature, value.to_lowercase()),
+  }
+}
+
+/// Decode a Robinson morphology code, as used by Greek New Testament texts, such as
+/// `V-AAI-3S` (verb, aorist active indicative, third person singular) or `N-GSM` (noun,
+/// genitive singular masculine). Codes in other schemes are only indexed whole.
+pub fn features(code: &str) -> Vec<String> {
+  let code = code.to_uppercase();
+  let mut features = vec![format!("morph:{}", code)];
+  let mut add = |feature: &str, value: Option<&str>| {
+    if let Some(value) = value {
+      features.push(format!("{}:{}", feature, value));
+    }
+  };
+
+  let mut parts = code.split('-');
+  let pos = parts.next().and_then(part_of_speech);
+  add("pos", pos);
+
+  match pos {
+    Some("verb") => {
+      let tvm = parts
+        .next()
+        .unwrap_or("")
+        .trim_start_matches(char::is_numeric);
+      let mut chars = tvm.chars();
+      add("tense", chars.next().and_then(tense));
+      add("voice", chars.next().and_then(voice));
+      let mood = chars.next().and_then(mood);
+      add("mood", mood);
+
+      let rest = parts.next().unwrap_or("");
+      if mood == Some("participle") {
+        add_case_number_gender(&mut add, rest);
+      } else {
+        let mut chars = rest.chars();
+        add("person", chars.next().and_then(person));
+        add("number", chars.next().and_then(number));
+      }
+    }
+    Some(_) => {
+      if let Some(rest) = parts.next() {
+        let rest = match rest.chars().next().and_then(person) {
+          Some(p) => {
+            add("person", Some(p));
+            &rest[1..]
+          }
+          None => rest,
+        };
+        add_case_number_gender(&mut add, rest);
+      }
+    }
+    None => (),
+  }
+
+  features
+}
+
+fn add_case_number_gender<F: FnMut(&str, Option<&str>)>(add: &mut F, code: &str) {
+  let mut chars = code.chars();
+  add("case", chars.next().and_then(case));
+  add("number", chars.next().and_then(number));
+  add("gender", chars.next().and_then(gender));
+}
+
+fn part_of_speech(code: &str) -> Option<&'static str> {
+  let pos = match code {
+    "N" => "noun",
+    "A" => "adjective",
+    "T" => "article",
+    "V" => "verb",
+    "P" => "personal_pronoun",
+    "R" => "relative_pronoun",
+    "C" => "reciprocal_pronoun",
+    "D" => "demonstrative_pronoun",
+    "K" | "Q" => "correlative_pronoun",
+    "I" => "interrogative_pronoun",
+    "X" => "indefinite_pronoun",
+    "F" => "reflexive_pronoun",
+    "S" => "possessive_pronoun",
+    "ADV" => "adverb",
+    "CONJ" => "conjunction",
+    "COND" => "conditional",
+    "PRT" => "particle",
+    "PREP" => "preposition",
+    "INJ" => "interjection",
+    _ => return None,
+  };
+
+  Some(pos)
+}
+
+fn tense(c: char) -> Option<&'static str> {
+  let tense = match c {
+    'P' => "present",
+    'I' => "imperfect",
+    'F' => "future",
+    'A' => "aorist",
+    'R' => "perfect",
+    'L' => "pluperfect",
+    _ => return None,
+  };
+
+  Some(tense)
+}
+
+fn voice(c: char) -> Option<&'static str> {
+  // Deponent verbs are counted with the voice of their form.
+  let voice = match c {
+    'A' | 'Q' => "active",
+    'M' | 'D' => "middle",
+    'P' | 'O' => "passive",
+    'E' | 'N' => "middle_passive",
+    _ => return None,
+  };
+
+  Some(voice)
+}
+
+fn mood(c: char) -> Option<&'static str> {
+  let mood = match c {
+    'I' => "indicative",
+    'S' => "subjunctive",
+    'O' => "optative",
+    'M' => "imperative",
+    'N' => "infinitive",
+    'P' | 'R' => "participle",
+    _ => return None,
+  };
+
+  Some(mood)
+}
+
+fn person(c: char) -> Option<&'static str> {
+  match c {
+    '1' => Some("1"),
+    '2' => Some("2"),
+    '3' => Some("3"),
+    _ => None,
+  }
+}
+
+fn number(c: char) -> Option<&'static str> {
+  match c {
+    'S' => Some("singular"),
+    'P' => Some("plural"),
+    _ => None,
+  }
+}
+
+fn case(c: char) -> Option<&'static str> {
+  let case = match c {
+    'N' => "nominative",
+    'G' => "genitive",
+    'D' => "dative",
+    'A' => "accusative",
+    'V' => "vocative",
+    _ => return None,
+  };
+
+  Some(case)
+}
+
+fn gender(c: char) -> Option<&'static str> {
+  let gender = match c {
+    'M' => "masculine",
+    'F' => "feminine",
+    'N' => "neuter",
+    _ => return None,
+  };
+
+  Some(gender)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decodes_verbs() {
+    assert_eq!(
+      features("V-AAI-3S"),
+      vec![
+        "morph:V-AAI-3S",
+        "pos:verb",
+        "tense:aorist",
+        "voice:active",
+        "mood:indicative",
+        "person:3",
+        "number:singular",
+      ]
+    );
+    // A second aorist, with the participle's case, number, and gender.
+    assert_eq!(
+      features("v-2aap-nsm"),
+      vec![
+        "morph:V-2AAP-NSM",
+        "pos:verb",
+        "tense:aorist",
+        "voice:active",
+        "mood:participle",
+        "case:nominative",
+        "number:singular",
+        "gender:masculine",
+      ]
+    );
+  }
+
+  #[test]
+  fn decodes_nouns_and_pronouns() {
+    assert_eq!(
+      features("N-GSF"),
+      vec![
+        "morph:N-GSF",
+        "pos:noun",
+        "case:genitive",
+        "number:singular",
+        "gender:feminine",
+      ]
+    );
+    assert_eq!(
+      features("P-1DP"),
+      vec![
+        "morph:P-1DP",
+        "pos:personal_pronoun",
+        "person:1",
+        "case:dative",
+        "number:plural",
+      ]
+    );
+    assert_eq!(features("CONJ"), vec!["morph:CONJ", "pos:conjunction"]);
+    assert_eq!(features("HVqp3ms"), vec!["morph:HVQP3MS"]);
+  }
+
+  #[test]
+  fn word_tokens_include_tags() {
+    let word = Word {
+      strong: vec![String::from("G0026")],
+      lemma: vec![String::from("ἈΓΆΠΗ")],
+      morph: vec![String::from("N-ASF")],
+      ..Word::untagged(0, 6)
+    };
+    let tokens = word_tokens(&word);
+    assert!(tokens.contains(&String::from("pos:noun")));
+    assert!(tokens.contains(&String::from("lemma:ἀγάπη")));
+    assert!(tokens.contains(&String::from("strong:G26")));
+    assert!(word_tokens(&Word::untagged(0, 1)).is_empty());
+  }
+
+  #[test]
+  fn query_tokens_match_indexed_tokens() {
+    assert_eq!(query_token("morph", "v-aai-3s"), "morph:V-AAI-3S");
+    assert_eq!(query_token("strong", "g0026"), "strong:G26");
+    assert_eq!(query_token("case", "Genitive"), "case:genitive");
+  }
+}
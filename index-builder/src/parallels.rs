@@ -0,0 +1,92 @@
+use crate::books::Location;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+/// A range of verses within one text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Span {
+  pub start: Location,
+  pub end: Location,
+}
+
+impl Span {
+  /// Whether the span has any verses between the (l0, l1, l2) coordinates `start` and
+  /// `end`, inclusive.
+  pub fn overlaps(&self, start: (usize, usize, usize), end: (usize, usize, usize)) -> bool {
+    self.start.first_verse() <= end && self.end.last_verse() >= start
+  }
+}
+
+/// Two passages in the same text that share a long run of words.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Parallel {
+  pub a: Span,
+  pub b: Span,
+  /// The number of tokens in the shared run.
+  pub len: usize,
+}
+
+/// A parallel seen from one of its passages.
+#[derive(Serialize, Debug)]
+pub struct ParallelMatch {
+  pub from: Span,
+  pub to: Span,
+  pub len: usize,
+}
+
+/// The parallel passages found in a text by the `analyze parallels` command, longest first.
+/// Each text's parallels are stored in `parallels-<book_id>.json` in the data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Parallels {
+  pub book_id: String,
+  pub parallels: Vec<Parallel>,
+}
+
+impl Parallels {
+  pub fn file_name(book_id: &str) -> String {
+    format!("parallels-{}.json", book_id)
+  }
+
+  pub fn load(path: &Path) -> Result<Parallels> {
+    let f = File::open(path)?;
+    Ok(serde_json::from_reader(f)?)
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join(Parallels::file_name(&self.book_id));
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+
+  /// The parallels with a passage between `start` and `end`, oriented so that `from` is
+  /// that passage.
+  pub fn range(
+    &self,
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+  ) -> Vec<ParallelMatch> {
+    self
+      .parallels
+      .iter()
+      .flat_map(|p| {
+        let forward = Some(ParallelMatch {
+          from: p.a,
+          to: p.b,
+          len: p.len,
+        })
+        .filter(|_| p.a.overlaps(start, end));
+        let backward = Some(ParallelMatch {
+          from: p.b,
+          to: p.a,
+          len: p.len,
+        })
+        .filter(|_| p.b.overlaps(start, end));
+        forward.into_iter().chain(backward)
+      })
+      .collect()
+  }
+}
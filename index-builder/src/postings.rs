@@ -0,0 +1,216 @@
+use crate::books::Location;
+use crate::error::Error;
+use crate::index::{Analyzer, DocKind};
+use anyhow::anyhow;
+use std::collections::{BTreeMap, HashMap};
+use tantivy::{
+  collector::TopDocs,
+  query::{BooleanQuery, Occur, Query, TermQuery},
+  schema::IndexRecordOption,
+  DocId, DocSet, Index, Postings, Term, TERMINATED,
+};
+
+/// The places that a term appears in one verse.
+#[derive(Debug, Clone)]
+pub struct Posting {
+  /// The index of the verse in `TextPostings::verses`.
+  pub verse: usize,
+  /// The token positions of the term in the verse.
+  pub positions: Vec<u32>,
+}
+
+#[derive(Debug)]
+pub struct TermPostings {
+  pub term: String,
+  /// The verses that the term appears in, in order.
+  pub postings: Vec<Posting>,
+}
+
+impl TermPostings {
+  /// The number of times the term appears in the verses from `verses.start` up to
+  /// `verses.end`.
+  pub fn freq(&self, verses: &std::ops::Range<usize>) -> usize {
+    let start = self.postings.partition_point(|p| p.verse < verses.start);
+    self.postings[start..]
+      .iter()
+      .take_while(|p| p.verse < verses.end)
+      .map(|p| p.positions.len())
+      .sum()
+  }
+}
+
+/// The terms of one text and where they appear, read from the term dictionary and postings
+/// of the field that the text was indexed in, for analyses that count words.
+pub struct TextPostings {
+  pub book_id: String,
+  pub analyzer: Analyzer,
+  /// The text's verses, in order.
+  pub verses: Vec<Location>,
+  /// The text's terms, in order.
+  pub terms: Vec<TermPostings>,
+}
+
+impl TextPostings {
+  pub fn load(index: &Index, book_id: &str) -> Result<TextPostings, Error> {
+    let reader = index.reader()?;
+    let searcher = reader.searcher();
+    let schema = index.schema();
+
+    let get_field = |f| {
+      schema
+        .get_field(f)
+        .ok_or_else(|| anyhow!("Failed to find '{}' field in index", f))
+    };
+    let book_field = get_field("book")?;
+    let kind_field = get_field("kind")?;
+    let l0_field = get_field("l0")?;
+    let l1_field = get_field("l1")?;
+    let l2_field = get_field("l2")?;
+
+    let term_query = |field, value: &str| -> Box<dyn Query> {
+      Box::new(TermQuery::new(
+        Term::from_field_text(field, value),
+        IndexRecordOption::Basic,
+      ))
+    };
+    let query = BooleanQuery::from(vec![
+      (Occur::Must, term_query(book_field, book_id)),
+      (Occur::Must, term_query(kind_field, DocKind::Verse.as_str())),
+    ]);
+
+    let limit = (searcher.num_docs() as usize).max(1);
+    let mut analyzer = None;
+    let mut docs = Vec::new();
+    for (_score, doc_address) in searcher.search(&query, &TopDocs::with_limit(limit))? {
+      let doc = searcher.doc(doc_address)?;
+      let level = |field| {
+        doc
+          .get_first(field)
+          .map(|l| l.u64_value() as usize)
+          .unwrap_or(0)
+      };
+      let location = Location {
+        l0: level(l0_field),
+        l1: level(l1_field),
+        l2: doc.get_first(l2_field).map(|l| l.u64_value() as usize),
+      };
+
+      if analyzer.is_none() {
+        for a in Analyzer::ALL.iter() {
+          if doc.get_first(get_field(a.field_name())?).is_some() {
+            analyzer = Some(*a);
+            break;
+          }
+        }
+      }
+
+      docs.push((location, doc_address));
+    }
+
+    if docs.is_empty() {
+      return Err(Error::Other(anyhow!("No verses found for {}", book_id)));
+    }
+
+    docs.sort_by_key(|(location, _)| location.first_verse());
+
+    // The verse of each document, by segment.
+    let mut segment_docs: BTreeMap<u32, HashMap<DocId, usize>> = BTreeMap::new();
+    for (verse, (_, doc_address)) in docs.iter().enumerate() {
+      segment_docs
+        .entry(doc_address.0)
+        .or_default()
+        .insert(doc_address.1, verse);
+    }
+
+    let analyzer = analyzer.unwrap_or_default();
+    let text_field = get_field(analyzer.field_name())?;
+    let mut terms: BTreeMap<String, Vec<Posting>> = BTreeMap::new();
+    for (segment_ord, doc_verses) in &segment_docs {
+      let inverted_index = searcher
+        .segment_reader(*segment_ord)
+        .inverted_index(text_field);
+      // A text is indexed all at once, so its documents are close together in each segment
+      // and most of each term's postings can be skipped.
+      let first_doc = doc_verses.keys().min().copied().unwrap_or(0);
+      let last_doc = doc_verses.keys().max().copied().unwrap_or(0);
+
+      let mut stream = inverted_index.terms().stream();
+      while stream.advance() {
+        let mut postings = inverted_index
+          .read_postings_from_terminfo(stream.value(), IndexRecordOption::WithFreqsAndPositions);
+        let mut term_postings = Vec::new();
+        let mut doc = postings.doc();
+        if doc < first_doc {
+          doc = postings.seek(first_doc);
+        }
+        while doc != TERMINATED && doc <= last_doc {
+          if let Some(&verse) = doc_verses.get(&doc) {
+            let mut positions = Vec::new();
+            postings.positions(&mut positions);
+            term_postings.push(Posting { verse, positions });
+          }
+          doc = postings.advance();
+        }
+
+        if !term_postings.is_empty() {
+          let term = String::from_utf8_lossy(stream.key()).into_owned();
+          terms.entry(term).or_default().extend(term_postings);
+        }
+      }
+    }
+
+    let terms = terms
+      .into_iter()
+      .map(|(term, mut postings)| {
+        postings.sort_by_key(|p| p.verse);
+        TermPostings { term, postings }
+      })
+      .collect();
+
+    Ok(TextPostings {
+      book_id: String::from(book_id),
+      analyzer,
+      verses: docs.into_iter().map(|(location, _)| location).collect(),
+      terms,
+    })
+  }
+
+  /// The terms that the text's analyzer makes of `text`, such as a word to look up.
+  pub fn analyze(&self, index: &Index, text: &str) -> Result<Vec<String>, Error> {
+    let field = index
+      .schema()
+      .get_field(self.analyzer.field_name())
+      .ok_or_else(|| {
+        anyhow!(
+          "Failed to find '{}' field in index",
+          self.analyzer.field_name()
+        )
+      })?;
+    let mut terms = Vec::new();
+    index
+      .tokenizer_for_field(field)?
+      .token_stream(text)
+      .process(&mut |token| terms.push(token.text.clone()));
+    Ok(terms)
+  }
+
+  pub fn term(&self, term: &str) -> Option<&TermPostings> {
+    self
+      .terms
+      .binary_search_by(|t| t.term.as_str().cmp(term))
+      .ok()
+      .map(|i| &self.terms[i])
+  }
+
+  /// The verses between the (l0, l1, l2) coordinates `start` and `end`, inclusive, as a
+  /// range of indexes into `verses`.
+  pub fn verse_range(
+    &self,
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+  ) -> std::ops::Range<usize> {
+    let first = self.verses.partition_point(|v| v.last_verse() < start);
+    let last = self.verses.partition_point(|v| v.first_verse() <= end);
+    first..last.max(first)
+  }
+}
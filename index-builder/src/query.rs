@@ -0,0 +1,704 @@
+use crate::error::Error;
+use crate::morphology::{query_token, FEATURES};
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use regex_syntax::hir::{Hir, HirKind, Look};
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use tantivy::{
+  query::{
+    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, FuzzyTermQuery, Occur, PhraseQuery, Query,
+    RangeQuery, TermQuery,
+  },
+  schema::{Field, FieldType, IndexRecordOption},
+  Index, Term,
+};
+use tantivy_fst::Automaton;
+use tantivy_query_grammar::{UserInputAST, UserInputBound, UserInputLeaf, UserInputLiteral};
+
+/// The most terms that a single fuzzy or prefix term will expand to.
+const MAX_EXPANSIONS: usize = 200;
+
+/// The largest edit distance supported by `FuzzyTermQuery`.
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+pub struct BuiltQuery {
+  /// The query that finds matching documents.
+  pub query: Box<dyn Query>,
+  /// A query containing every term that can match, for use with `SnippetGenerator`. Fuzzy
+  /// terms don't report the terms they match, so this adds them from the term dictionary.
+  pub highlight: Box<dyn Query>,
+}
+
+enum Expansion {
+  Fuzzy(u8),
+  Prefix,
+}
+
+/// A Levenshtein automaton that can search a term dictionary, built the same way as the
+/// one that `FuzzyTermQuery` uses, so that it finds the same terms.
+struct FuzzyAutomaton(DFA);
+
+impl FuzzyAutomaton {
+  fn new(word: &str, distance: u8) -> Self {
+    FuzzyAutomaton(LevenshteinAutomatonBuilder::new(distance, true).build_dfa(word))
+  }
+}
+
+impl Automaton for FuzzyAutomaton {
+  type State = u32;
+
+  fn start(&self) -> u32 {
+    self.0.initial_state()
+  }
+
+  fn is_match(&self, state: &u32) -> bool {
+    matches!(self.0.distance(*state), Distance::Exact(_))
+  }
+
+  fn can_match(&self, state: &u32) -> bool {
+    *state != levenshtein_automata::SINK_STATE
+  }
+
+  fn accept(&self, state: &u32, byte: u8) -> u32 {
+    self.0.transition(*state, byte)
+  }
+}
+
+/// Builds queries from tantivy's query syntax, extended with fuzzy (`word~1`) and
+/// prefix (`word*`) terms, and morphology features (`case:genitive`), which can be combined
+/// with `lemma:` and `strong:` to describe a single word.
+///
+/// The query is built separately for each default field, and a document matches if it
+/// matches in any of them. Words are run through each field's own tokenizer, and a word
+/// that the tokenizer drops, such as a stopword, is left out of that field's query.
+pub struct QueryBuilder<'a> {
+  index: &'a Index,
+  searcher: &'a tantivy::Searcher,
+  default_fields: Vec<Field>,
+}
+
+impl<'a> QueryBuilder<'a> {
+  pub fn new(
+    index: &'a Index,
+    searcher: &'a tantivy::Searcher,
+    default_fields: Vec<Field>,
+  ) -> Self {
+    QueryBuilder {
+      index,
+      searcher,
+      default_fields,
+    }
+  }
+
+  pub fn build(&self, query_text: &str) -> Result<BuiltQuery, Error> {
+    let ast =
+      tantivy_query_grammar::parse_query(query_text).map_err(|_e| Error::QueryParseError)?;
+    self.build_query(&ast)
+  }
+
+  /// Split a query into its top-level clauses so that each can be matched separately, with
+  /// the occurrence each one had in the query. A query that is a disjunction stays whole.
+  /// Bare `NEAR` words are dropped, so "faith NEAR love" splits into "faith" and "love".
+  pub fn build_parts(&self, query_text: &str) -> Result<Vec<(Occur, BuiltQuery)>, Error> {
+    let ast =
+      tantivy_query_grammar::parse_query(query_text).map_err(|_e| Error::QueryParseError)?;
+    let clauses = match ast {
+      UserInputAST::Clause(clauses)
+        if clauses
+          .iter()
+          .all(|(occur, _)| *occur != Some(Occur::Should)) =>
+      {
+        clauses
+      }
+      ast => vec![(None, ast)],
+    };
+
+    clauses
+      .iter()
+      .filter(|(_, ast)| !is_near_operator(ast))
+      .map(|(occur, ast)| Ok((occur.unwrap_or(Occur::Must), self.build_query(ast)?)))
+      .collect()
+  }
+
+  fn build_query(&self, ast: &UserInputAST) -> Result<BuiltQuery, Error> {
+    let mut expanded_terms = Vec::new();
+    let mut field_queries = Vec::new();
+    for &field in &self.default_fields {
+      if let Some(query) = self.build_ast(ast, field, &mut expanded_terms)? {
+        field_queries.push((Occur::Should, query));
+      }
+    }
+
+    let query: Box<dyn Query> = match field_queries.len() {
+      0 => Box::new(EmptyQuery),
+      1 => field_queries.remove(0).1,
+      _ => Box::new(BooleanQuery::from(field_queries)),
+    };
+
+    expanded_terms.sort();
+    expanded_terms.dedup();
+    let highlight: Box<dyn Query> = if expanded_terms.is_empty() {
+      query.box_clone()
+    } else {
+      let mut clauses = vec![(Occur::Should, query.box_clone())];
+      clauses.extend(expanded_terms.into_iter().map(|term| {
+        let q: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+        (Occur::Should, q)
+      }));
+      Box::new(BooleanQuery::from(clauses))
+    };
+
+    Ok(BuiltQuery { query, highlight })
+  }
+
+  /// Build the query for `ast` in `field`, or None if the field's tokenizer drops every word
+  /// in it.
+  fn build_ast(
+    &self,
+    ast: &UserInputAST,
+    field: Field,
+    expanded_terms: &mut Vec<Term>,
+  ) -> Result<Option<Box<dyn Query>>, Error> {
+    match ast {
+      UserInputAST::Clause(clauses) => {
+        // Required morphology features all apply to the same word, along with any required
+        // lemma or Strong's number.
+        let is_required = |occur: &Option<Occur>| occur.unwrap_or(Occur::Must) == Occur::Must;
+        let has_morphology = clauses.iter().any(|(occur, ast)| {
+          is_required(occur) && matches!(word_feature(ast), Some((f, _)) if FEATURES.contains(&f))
+        });
+        let (word_clauses, clauses): (Vec<_>, Vec<_>) = clauses.iter().partition(|(occur, ast)| {
+          has_morphology && is_required(occur) && word_feature(ast).is_some()
+        });
+
+        let mut subqueries = Vec::new();
+        if !word_clauses.is_empty() {
+          let features = word_clauses
+            .iter()
+            .filter_map(|(_, ast)| word_feature(ast))
+            .collect::<Vec<_>>();
+          subqueries.push((Occur::Must, self.build_word_query(&features)?));
+        }
+
+        for (occur, ast) in clauses {
+          // Match the parser's behavior with `set_conjunction_by_default`.
+          let occur = occur.unwrap_or(Occur::Must);
+          if let Some(query) = self.build_ast(ast, field, expanded_terms)? {
+            subqueries.push((occur, query));
+          }
+        }
+
+        if subqueries.is_empty() {
+          return Ok(None);
+        }
+        Ok(Some(Box::new(BooleanQuery::from(subqueries))))
+      }
+      UserInputAST::Boost(ast, boost) => Ok(
+        self
+          .build_ast(ast, field, expanded_terms)?
+          .map(|query| -> Box<dyn Query> { Box::new(BoostQuery::new(query, *boost as f32)) }),
+      ),
+      UserInputAST::Leaf(_) if matches!(word_feature(ast), Some((f, _)) if FEATURES.contains(&f)) =>
+      {
+        let feature = word_feature(ast).unwrap();
+        self.build_word_query(&[feature]).map(Some)
+      }
+      UserInputAST::Leaf(leaf) => match leaf.as_ref() {
+        UserInputLeaf::Literal(literal) => match split_expansion(&literal.phrase) {
+          Some((word, expansion)) => {
+            self.build_expansion(literal, field, word, expansion, expanded_terms)
+          }
+          None => self.build_literal(literal, field),
+        },
+        UserInputLeaf::All => Ok(Some(Box::new(AllQuery))),
+        UserInputLeaf::Range {
+          field: field_name,
+          lower,
+          upper,
+        } => {
+          let field = match field_name {
+            Some(name) => self.get_field(name)?,
+            None => field,
+          };
+          let bound = |bound: &UserInputBound| -> Result<Bound<Term>, Error> {
+            let term = |text: &str| -> Result<Term, Error> {
+              match self.field_terms(field, text)?.as_slice() {
+                [(_, term)] => Ok(term.clone()),
+                _ => Err(Error::QueryParseError),
+              }
+            };
+            Ok(match bound {
+              UserInputBound::Inclusive(text) => Bound::Included(term(text)?),
+              UserInputBound::Exclusive(text) => Bound::Excluded(term(text)?),
+              UserInputBound::Unbounded => Bound::Unbounded,
+            })
+          };
+
+          let value_type = self
+            .index
+            .schema()
+            .get_field_entry(field)
+            .field_type()
+            .value_type();
+          Ok(Some(Box::new(RangeQuery::new_term_bounds(
+            field,
+            value_type,
+            &bound(lower)?,
+            &bound(upper)?,
+          ))))
+        }
+      },
+    }
+  }
+
+  /// Build a query for a word that has every one of the features, using the morphology
+  /// field, which indexes all of a word's features at the same position.
+  fn build_word_query(&self, features: &[(&str, &str)]) -> Result<Box<dyn Query>, Error> {
+    let field = self.get_field("morph")?;
+    let mut terms = features
+      .iter()
+      .map(|(feature, value)| Term::from_field_text(field, &query_token(feature, value)))
+      .collect::<Vec<_>>();
+    terms.sort();
+    terms.dedup();
+
+    if terms.len() == 1 {
+      return Ok(Box::new(TermQuery::new(
+        terms.remove(0),
+        IndexRecordOption::WithFreqs,
+      )));
+    }
+
+    // A phrase whose terms all have the same offset matches when they share a position.
+    let terms = terms.into_iter().map(|term| (0, term)).collect::<Vec<_>>();
+    Ok(Box::new(PhraseQuery::new_with_offset(terms)))
+  }
+
+  /// Build a query for a word or phrase, in its own field or else in `default_field`.
+  fn build_literal(
+    &self,
+    literal: &UserInputLiteral,
+    default_field: Field,
+  ) -> Result<Option<Box<dyn Query>>, Error> {
+    let field = match &literal.field_name {
+      Some(name) => self.get_field(name)?,
+      None => default_field,
+    };
+
+    let mut terms = self.field_terms(field, &literal.phrase)?;
+    let index_option = match self.index.schema().get_field_entry(field).field_type() {
+      FieldType::Str(options) => options
+        .get_indexing_options()
+        .map(|o| o.index_option())
+        .unwrap_or(IndexRecordOption::Basic),
+      _ => IndexRecordOption::Basic,
+    };
+
+    match terms.len() {
+      0 => Ok(None),
+      1 => Ok(Some(Box::new(TermQuery::new(
+        terms.remove(0).1,
+        index_option,
+      )))),
+      _ if index_option.has_positions() => Ok(Some(Box::new(PhraseQuery::new_with_offset(terms)))),
+      _ => Err(Error::QueryParseError),
+    }
+  }
+
+  /// The terms, with their positions, that a value becomes in a field. Text is run through
+  /// the field's tokenizer, as it was when indexed.
+  fn field_terms(&self, field: Field, text: &str) -> Result<Vec<(usize, Term)>, Error> {
+    let schema = self.index.schema();
+    match schema.get_field_entry(field).field_type() {
+      FieldType::Str(options) if options.get_indexing_options().is_some() => {
+        let mut terms = Vec::new();
+        self
+          .index
+          .tokenizer_for_field(field)?
+          .token_stream(text)
+          .process(&mut |token| {
+            terms.push((token.position, Term::from_field_text(field, &token.text)))
+          });
+        Ok(terms)
+      }
+      FieldType::U64(options) if options.is_indexed() => {
+        let value = text.parse::<u64>().map_err(|_e| Error::QueryParseError)?;
+        Ok(vec![(0, Term::from_field_u64(field, value))])
+      }
+      FieldType::I64(options) if options.is_indexed() => {
+        let value = text.parse::<i64>().map_err(|_e| Error::QueryParseError)?;
+        Ok(vec![(0, Term::from_field_i64(field, value))])
+      }
+      _ => Err(Error::QueryParseError),
+    }
+  }
+
+  fn get_field(&self, name: &str) -> Result<Field, Error> {
+    self
+      .index
+      .schema()
+      .get_field(name)
+      .ok_or(Error::QueryParseError)
+  }
+
+  fn field_for(&self, literal: &UserInputLiteral, default_field: Field) -> Result<Field, Error> {
+    match &literal.field_name {
+      Some(name) => self.get_field(name),
+      None => Ok(default_field),
+    }
+  }
+
+  fn build_expansion(
+    &self,
+    literal: &UserInputLiteral,
+    default_field: Field,
+    word: &str,
+    expansion: Expansion,
+    expanded_terms: &mut Vec<Term>,
+  ) -> Result<Option<Box<dyn Query>>, Error> {
+    let field = self.field_for(literal, default_field)?;
+    match expansion {
+      Expansion::Prefix => {
+        // Don't stem a prefix, since the stemmer would treat it as a complete word.
+        let prefix = word.to_lowercase();
+        let subqueries = self
+          .prefix_terms(field, &prefix)
+          .into_iter()
+          .map(|term| {
+            let q: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+            (Occur::Should, q)
+          })
+          .collect::<Vec<_>>();
+
+        if subqueries.is_empty() {
+          return Ok(Some(Box::new(EmptyQuery)));
+        }
+        Ok(Some(Box::new(BooleanQuery::from(subqueries))))
+      }
+      Expansion::Fuzzy(distance) => {
+        let analyzed = match self.analyze(field, word)? {
+          Some(a) => a,
+          None => return Ok(None),
+        };
+
+        expanded_terms.extend(self.fuzzy_terms(field, &analyzed, distance));
+
+        let term = Term::from_field_text(field, &analyzed);
+        Ok(Some(Box::new(FuzzyTermQuery::new(term, distance, true))))
+      }
+    }
+  }
+
+  /// Build a query that finds a superset of the documents that a regular expression can
+  /// match, using whole words that the expression requires. When it requires none, this
+  /// matches every document.
+  pub fn regex_prefilter(&self, pattern: &str) -> Result<Box<dyn Query>, Error> {
+    let hir = regex_syntax::parse(pattern).map_err(|_e| Error::QueryParseError)?;
+
+    let mut clauses = Vec::new();
+    for word in required_words(&hir) {
+      let mut field_clauses = Vec::new();
+      for &field in &self.default_fields {
+        match self.analyze(field, &word)? {
+          Some(analyzed) => {
+            let term = Term::from_field_text(field, &analyzed);
+            let q: Box<dyn Query> = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+            field_clauses.push((Occur::Should, q));
+          }
+          // A stopword isn't indexed, so it can't be used to filter documents in this field.
+          None => break,
+        }
+      }
+
+      if field_clauses.len() == self.default_fields.len() {
+        let q: Box<dyn Query> = Box::new(BooleanQuery::from(field_clauses));
+        clauses.push((Occur::Must, q));
+      }
+    }
+
+    if clauses.is_empty() {
+      return Ok(Box::new(AllQuery));
+    }
+
+    Ok(Box::new(BooleanQuery::from(clauses)))
+  }
+
+  /// Run a word through the field's tokenizer, so that it matches the indexed form.
+  fn analyze(&self, field: Field, word: &str) -> Result<Option<String>, Error> {
+    let tokenizer = self.index.tokenizer_for_field(field)?;
+    let mut stream = tokenizer.token_stream(word);
+    Ok(stream.next().map(|token| token.text.clone()))
+  }
+
+  /// Find the terms in the field's term dictionary that start with `prefix`.
+  fn prefix_terms(&self, field: Field, prefix: &str) -> Vec<Term> {
+    let mut found = BTreeSet::new();
+    for segment in self.searcher.segment_readers() {
+      let inverted_index = segment.inverted_index(field);
+      let mut stream = inverted_index.terms().range().ge(prefix).into_stream();
+      while stream.advance() && found.len() < MAX_EXPANSIONS {
+        let key = match std::str::from_utf8(stream.key()) {
+          Ok(k) => k,
+          Err(_) => continue,
+        };
+
+        if !key.starts_with(prefix) {
+          break;
+        }
+
+        found.insert(String::from(key));
+      }
+    }
+
+    found
+      .into_iter()
+      .map(|t| Term::from_field_text(field, &t))
+      .collect()
+  }
+
+  /// Find the terms in the field's term dictionary that a fuzzy term matches. The automaton
+  /// only visits the parts of the dictionary that can match.
+  fn fuzzy_terms(&self, field: Field, word: &str, distance: u8) -> Vec<Term> {
+    let automaton = FuzzyAutomaton::new(word, distance);
+    let mut found = BTreeSet::new();
+    for segment in self.searcher.segment_readers() {
+      let inverted_index = segment.inverted_index(field);
+      let mut stream = inverted_index.terms().search(&automaton).into_stream();
+      while stream.advance() && found.len() < MAX_EXPANSIONS {
+        if let Ok(key) = std::str::from_utf8(stream.key()) {
+          found.insert(String::from(key));
+        }
+      }
+    }
+
+    found
+      .into_iter()
+      .map(|t| Term::from_field_text(field, &t))
+      .collect()
+  }
+}
+
+/// Return the feature and value of a leaf that constrains a single word, such as
+/// `case:genitive` or `lemma:λόγος`.
+fn word_feature(ast: &UserInputAST) -> Option<(&str, &str)> {
+  match ast {
+    UserInputAST::Leaf(leaf) => match leaf.as_ref() {
+      UserInputLeaf::Literal(UserInputLiteral {
+        field_name: Some(field),
+        phrase,
+      }) if FEATURES.contains(&field.as_str()) || field == "lemma" || field == "strong" => {
+        Some((field.as_str(), phrase.as_str()))
+      }
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn is_near_operator(ast: &UserInputAST) -> bool {
+  match ast {
+    UserInputAST::Leaf(leaf) => matches!(
+      leaf.as_ref(),
+      UserInputLeaf::Literal(UserInputLiteral {
+        field_name: None,
+        phrase,
+      }) if phrase == "NEAR"
+    ),
+    _ => false,
+  }
+}
+
+fn unwrap_capture(hir: &Hir) -> &Hir {
+  match hir.kind() {
+    HirKind::Capture(capture) => unwrap_capture(&capture.sub),
+    _ => hir,
+  }
+}
+
+fn is_boundary(hir: Option<&Hir>) -> bool {
+  match hir.map(|h| unwrap_capture(h).kind()) {
+    Some(HirKind::Look(look)) => matches!(
+      look,
+      Look::Start
+        | Look::End
+        | Look::StartLF
+        | Look::EndLF
+        | Look::WordAscii
+        | Look::WordUnicode
+        | Look::WordStartAscii
+        | Look::WordEndAscii
+        | Look::WordStartUnicode
+        | Look::WordEndUnicode
+    ),
+    _ => false,
+  }
+}
+
+/// Find the whole words that must appear in any text matched by the expression. This only
+/// looks at literals in the top-level sequence, and only takes words that are delimited
+/// within the literal or by an anchor or word boundary, since otherwise the literal may
+/// only be part of a word.
+fn required_words(hir: &Hir) -> Vec<String> {
+  let items = match unwrap_capture(hir).kind() {
+    HirKind::Concat(items) => items.iter().collect::<Vec<_>>(),
+    _ => vec![hir],
+  };
+
+  let mut words = Vec::new();
+  for (i, item) in items.iter().enumerate() {
+    let literal = match unwrap_capture(item).kind() {
+      HirKind::Literal(literal) => literal,
+      _ => continue,
+    };
+
+    let text = match std::str::from_utf8(&literal.0) {
+      Ok(t) => t,
+      Err(_) => continue,
+    };
+
+    let bounded_before = i > 0 && is_boundary(items.get(i - 1).copied());
+    let bounded_after = is_boundary(items.get(i + 1).copied());
+
+    let mut offset = 0;
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+      let start = offset;
+      let end = start + word.len();
+      offset = end
+        + text[end..]
+          .chars()
+          .next()
+          .map(|c| c.len_utf8())
+          .unwrap_or(0);
+
+      if word.is_empty() {
+        continue;
+      }
+
+      let starts_word = start > 0 || bounded_before;
+      let ends_word = end < text.len() || bounded_after;
+      if starts_word && ends_word {
+        words.push(String::from(word));
+      }
+    }
+  }
+
+  words
+}
+
+/// Split a literal like `word~1` or `word*` into the word and how to expand it.
+fn split_expansion(phrase: &str) -> Option<(&str, Expansion)> {
+  if phrase.contains(char::is_whitespace) {
+    return None;
+  }
+
+  if let Some(word) = phrase.strip_suffix('*') {
+    return Some((word, Expansion::Prefix)).filter(|(w, _)| !w.is_empty());
+  }
+
+  let (word, distance) = phrase.rsplit_once('~')?;
+  let distance = match distance {
+    "" => 1,
+    d => d.parse::<u8>().ok()?.min(MAX_FUZZY_DISTANCE),
+  };
+
+  Some((word, Expansion::Fuzzy(distance))).filter(|(w, _)| !w.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::index::{open_index, Analyzer};
+  use tantivy::{collector::Count, Document};
+
+  fn accepts(automaton: &FuzzyAutomaton, word: &str) -> bool {
+    let state = word.bytes().fold(automaton.start(), |state, byte| {
+      automaton.accept(&state, byte)
+    });
+    automaton.is_match(&state)
+  }
+
+  #[test]
+  fn fuzzy_automaton_matches_within_distance() {
+    let automaton = FuzzyAutomaton::new("love", 1);
+    assert!(accepts(&automaton, "love"));
+    assert!(accepts(&automaton, "loves"));
+    assert!(accepts(&automaton, "olve"));
+    assert!(!accepts(&automaton, "lo"));
+    assert!(!accepts(&automaton, "alive"));
+
+    let automaton = FuzzyAutomaton::new("love", 2);
+    assert!(accepts(&automaton, "alive"));
+  }
+
+  #[test]
+  fn split_expansion_reads_suffixes() {
+    assert!(matches!(
+      split_expansion("love*"),
+      Some(("love", Expansion::Prefix))
+    ));
+    assert!(matches!(
+      split_expansion("love~"),
+      Some(("love", Expansion::Fuzzy(1)))
+    ));
+    assert!(matches!(
+      split_expansion("love~2"),
+      Some(("love", Expansion::Fuzzy(2)))
+    ));
+    assert!(matches!(
+      split_expansion("love~5"),
+      Some(("love", Expansion::Fuzzy(MAX_FUZZY_DISTANCE)))
+    ));
+    assert!(split_expansion("love").is_none());
+    assert!(split_expansion("*").is_none());
+    assert!(split_expansion("love~x").is_none());
+    assert!(split_expansion("in the*").is_none());
+  }
+
+  #[test]
+  fn required_words_only_takes_whole_words() {
+    let words = |pattern| required_words(&regex_syntax::parse(pattern).unwrap());
+    assert_eq!(
+      words(r"\bin the beginning\b"),
+      vec!["in", "the", "beginning"]
+    );
+    assert_eq!(words("in the beginning"), vec!["the"]);
+    assert_eq!(words(r"^love.*one another$"), vec!["another"]);
+    assert_eq!(words(r"^love\b.*"), vec!["love"]);
+    assert!(words("lov(e|ed)").is_empty());
+  }
+
+  #[test]
+  fn stopwords_are_dropped_per_field() {
+    let dir = std::env::temp_dir().join("query_stopwords");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let index = open_index(&dir).unwrap();
+    let schema = index.schema();
+    let text = schema.get_field(Analyzer::Standard.field_name()).unwrap();
+    let archaic = schema.get_field(Analyzer::Archaic.field_name()).unwrap();
+
+    let mut writer = index.writer_with_num_threads(1, 10_000_000).unwrap();
+    let mut doc = Document::default();
+    doc.add_text(text, "God is love");
+    writer.add_document(doc);
+    let mut doc = Document::default();
+    doc.add_text(archaic, "I will be with thee");
+    writer.add_document(doc);
+    writer.commit().unwrap();
+
+    let searcher = index.reader().unwrap().searcher();
+    let builder = QueryBuilder::new(&index, &searcher, vec![text, archaic]);
+    let count = |query: &str| {
+      let built = builder.build(query).unwrap();
+      searcher.search(&built.query, &Count).unwrap()
+    };
+
+    // "the" is a stopword in both fields, so it doesn't have to match.
+    assert_eq!(count("the love"), 1);
+    assert_eq!(count("+the +love"), 1);
+    assert_eq!(count("the"), 0);
+    // "thee" becomes "you", which the archaic field keeps.
+    assert_eq!(count("thee"), 1);
+    assert_eq!(count("thee OR love"), 2);
+  }
+}
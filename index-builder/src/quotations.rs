@@ -0,0 +1,45 @@
+use crate::books::Location;
+use crate::parallels::Span;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+/// A place where a text quotes a range of verses.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Quotation {
+  /// The location in the quoting text.
+  pub source: Location,
+  /// The quoted verses in the bible.
+  pub verses: Span,
+  /// The fraction of the verses' shingles that appear in the quoting passage, from 0 to 1.
+  pub similarity: f32,
+}
+
+/// The quotations of a bible found in another text by the `analyze quotations` command.
+/// They are stored in `quotations-<source_id>-<bible_id>.json` in the data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Quotations {
+  pub source_id: String,
+  pub bible_id: String,
+  pub quotations: Vec<Quotation>,
+}
+
+impl Quotations {
+  pub fn file_name(source_id: &str, bible_id: &str) -> String {
+    format!("quotations-{}-{}.json", source_id, bible_id)
+  }
+
+  pub fn load(path: &Path) -> Result<Quotations> {
+    let f = File::open(path)?;
+    Ok(serde_json::from_reader(f)?)
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join(Quotations::file_name(&self.source_id, &self.bible_id));
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+}
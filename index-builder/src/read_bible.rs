@@ -1,13 +1,65 @@
-use anyhow::{Context, Result};
+use study_map_index::index::Word;
+use anyhow::{anyhow, Context, Result};
 use quick_xml::events::Event;
 use std::path::Path;
 
-fn get_name<'a>(e: &'a quick_xml::events::BytesStart) -> String {
+fn get_attr<'a>(e: &'a quick_xml::events::BytesStart, key: &[u8]) -> Option<String> {
     e.attributes()
         .map(|a| a.unwrap())
-        .find(|a| a.key == b"n")
+        .find(|a| a.key == key)
         .map(|a| String::from_utf8_lossy(&a.unescaped_value().unwrap()).to_string())
-        .unwrap()
+}
+
+fn get_name<'a>(e: &'a quick_xml::events::BytesStart) -> String {
+    get_attr(e, b"n").unwrap()
+}
+
+/// Whether an element marks the words of Christ, as in OSIS `<q who="Jesus">` or Zefania
+/// `<STYLE css="color:red">`. Returns None for elements that aren't quotes or styles.
+fn is_red_letter(e: &quick_xml::events::BytesStart) -> Option<bool> {
+    match e.name() {
+        b"q" => Some(get_attr(e, b"who").as_deref() == Some("Jesus")),
+        b"STYLE" => Some(matches!(get_attr(e, b"css"), Some(css) if css.contains("red"))),
+        _ => None,
+    }
+}
+
+/// Parse a `<w>` element's attributes into a word starting at `start`. The lemma attribute
+/// holds a space-separated list, where Strong's numbers are prefixed with "strong:", and
+/// lemmas are either bare, as in the SBLGNT, or prefixed with "lemma" and a source, as in
+/// `lemma.TR:αγαπη`. The morph attribute holds morphology codes, optionally prefixed with
+/// their scheme, as in `robinson:V-AAI-3S`, and the xlit attribute holds a transliteration
+/// of the original word, optionally prefixed with its scheme, as in `xlit="Latn:agapēn"`.
+fn parse_word(e: &quick_xml::events::BytesStart, start: usize) -> Word {
+    let mut word = Word {
+        start,
+        end: start,
+        strong: Vec::new(),
+        lemma: Vec::new(),
+        morph: Vec::new(),
+        xlit: get_attr(e, b"xlit")
+            .map(|xlit| String::from(xlit.split_once(':').map_or(xlit.as_str(), |(_, x)| x))),
+    };
+
+    let lemma_attr = get_attr(e, b"lemma").unwrap_or_default();
+    for part in lemma_attr.split_whitespace() {
+        match part.split_once(':') {
+            Some(("strong", number)) => word.strong.push(String::from(number)),
+            Some((prefix, lemma)) if prefix.starts_with("lemma") => {
+                word.lemma.push(String::from(lemma))
+            }
+            Some(_) => (),
+            None => word.lemma.push(String::from(part)),
+        }
+    }
+
+    let morph_attr = get_attr(e, b"morph").unwrap_or_default();
+    for part in morph_attr.split_whitespace() {
+        let code = part.split_once(':').map_or(part, |(_, code)| code);
+        word.morph.push(String::from(code));
+    }
+
+    word
 }
 
 pub struct Passage<'a> {
@@ -16,6 +68,100 @@ pub struct Passage<'a> {
     pub chapter: usize,
     pub verse: usize,
     pub text: String,
+    /// The section heading that starts at this verse, if any.
+    pub heading: Option<String>,
+    /// Footnotes and translator notes attached to this verse.
+    pub notes: Vec<String>,
+    /// The byte ranges of the text that are words of Christ.
+    pub red_letter: Vec<(usize, usize)>,
+    /// The tagged words in the text, in order.
+    pub words: Vec<Word>,
+}
+
+/// Elements that hold a section heading rather than verse text.
+fn is_heading(name: &[u8]) -> bool {
+    matches!(name, b"title" | b"caption" | b"CAPTION")
+}
+
+/// The start or end of a book, chapter, or verse.
+enum Boundary {
+    Book(String),
+    Chapter(usize),
+    Verse(usize),
+    BookEnd,
+    ChapterEnd,
+    VerseEnd,
+}
+
+/// The chapter or verse number at the end of an OSIS ID such as "Gen.1.1". An element that
+/// covers several verses lists all of their IDs, and this uses the first one.
+fn osis_number(e: &quick_xml::events::BytesStart) -> Result<usize> {
+    let id = get_attr(e, b"osisID")
+        .or_else(|| get_attr(e, b"sID"))
+        .ok_or_else(|| anyhow!("Missing osisID"))?;
+    let number = id
+        .split_whitespace()
+        .next()
+        .and_then(|id| id.rsplit('.').next())
+        .unwrap_or_default();
+    number
+        .parse::<usize>()
+        .with_context(|| format!("Invalid osisID {}", id))
+}
+
+/// Find where books, chapters, and verses start and end. This understands the simple
+/// format of `<b n>`, `<c n>`, and `<v n>` elements, OSIS, with `<div type="book">`,
+/// `<chapter>`, and `<verse>` elements or milestones, and Zefania XML, with `<BIBLEBOOK>`,
+/// `<CHAPTER>`, and `<VERS>` elements.
+fn boundary(event: &Event) -> Result<Option<Boundary>> {
+    let number = |e: &quick_xml::events::BytesStart, key: &[u8]| -> Result<usize> {
+        let value = get_attr(e, key).unwrap_or_default();
+        value
+            .parse::<usize>()
+            .with_context(|| format!("Invalid number '{}'", value))
+    };
+
+    let boundary = match event {
+        Event::Start(e) => match e.name() {
+            b"b" => Some(Boundary::Book(get_name(e))),
+            b"BIBLEBOOK" => get_attr(e, b"bname")
+                .or_else(|| get_attr(e, b"bnumber"))
+                .map(Boundary::Book),
+            b"div" if get_attr(e, b"type").as_deref() == Some("book") => {
+                get_attr(e, b"osisID").map(Boundary::Book)
+            }
+            b"c" => Some(Boundary::Chapter(number(e, b"n")?)),
+            b"CHAPTER" => Some(Boundary::Chapter(number(e, b"cnumber")?)),
+            b"chapter" => Some(Boundary::Chapter(osis_number(e)?)),
+            b"v" => Some(Boundary::Verse(number(e, b"n")?)),
+            b"VERS" => Some(Boundary::Verse(number(e, b"vnumber")?)),
+            b"verse" => Some(Boundary::Verse(osis_number(e)?)),
+            _ => None,
+        },
+        // OSIS milestones, which mark the start and end of a chapter or verse with empty
+        // elements.
+        Event::Empty(e) => match e.name() {
+            b"chapter" if get_attr(e, b"sID").is_some() => Some(Boundary::Chapter(osis_number(e)?)),
+            b"chapter" if get_attr(e, b"eID").is_some() => Some(Boundary::ChapterEnd),
+            b"verse" if get_attr(e, b"sID").is_some() => Some(Boundary::Verse(osis_number(e)?)),
+            b"verse" if get_attr(e, b"eID").is_some() => Some(Boundary::VerseEnd),
+            _ => None,
+        },
+        Event::End(e) => match e.name() {
+            b"b" | b"BIBLEBOOK" => Some(Boundary::BookEnd),
+            b"c" | b"CHAPTER" | b"chapter" => Some(Boundary::ChapterEnd),
+            b"v" | b"VERS" | b"verse" => Some(Boundary::VerseEnd),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    Ok(boundary)
+}
+
+/// Elements that hold a note, which is kept separate from the verse text.
+fn is_note(name: &[u8]) -> bool {
+    matches!(name, b"note" | b"NOTE")
 }
 
 pub fn read<F: FnMut(Passage) -> Result<()>>(path: &Path, mut callback: F) -> Result<()> {
@@ -28,42 +174,153 @@ pub fn read<F: FnMut(Passage) -> Result<()>>(path: &Path, mut callback: F) -> Re
 
     let mut book_index = 0;
 
+    let mut in_heading = false;
+    let mut heading = String::new();
+    let mut pending_heading: Option<String> = None;
+
+    // A verse may be split into several text events by notes and other markup, so its text
+    // is collected until the verse ends.
+    let mut text = String::new();
+    let mut in_note = false;
+    let mut note = String::new();
+    let mut notes = Vec::new();
+    // Whether each open quote or style element is red letter.
+    let mut styles: Vec<bool> = Vec::new();
+    // OSIS quotes that cross other elements are written as empty start and end milestones,
+    // which are matched by ID.
+    let mut milestones: Vec<(String, bool)> = Vec::new();
+    let mut red_letter: Vec<(usize, usize)> = Vec::new();
+    let mut word: Option<Word> = None;
+    let mut words = Vec::new();
+
     let mut buf = Vec::new();
     loop {
-        match reader.read_event(&mut buf) {
-            Ok(Event::Start(ref e)) => match e.name() {
-                b"b" => {
-                    let new_book = get_name(e);
+        let event = reader.read_event(&mut buf);
+        if let Ok(ref event) = event {
+            match boundary(event)? {
+                Some(Boundary::Book(new_book)) => {
                     if new_book != current_book {
                         current_book = new_book;
                         book_index += 1;
                     }
+                    continue;
                 }
-                b"c" => {
-                    current_chapter = get_name(e).parse::<usize>()?;
+                Some(Boundary::Chapter(chapter)) => {
+                    current_chapter = chapter;
+                    continue;
                 }
-                b"v" => {
-                    current_verse = get_name(e).parse::<usize>()?;
+                Some(Boundary::Verse(verse)) => {
+                    current_verse = verse;
+                    text.clear();
+                    notes.clear();
+                    red_letter.clear();
+                    words.clear();
+                    continue;
+                }
+                Some(Boundary::BookEnd) => {
+                    current_book = String::new();
+                    continue;
+                }
+                Some(Boundary::ChapterEnd) => {
+                    current_chapter = 0;
+                    continue;
+                }
+                Some(Boundary::VerseEnd) => {
+                    if current_book.len() > 0 && current_chapter > 0 && current_verse > 0 {
+                        callback(Passage {
+                            book: current_book.as_ref(),
+                            book_index,
+                            chapter: current_chapter,
+                            verse: current_verse,
+                            text: std::mem::take(&mut text),
+                            heading: pending_heading.take(),
+                            notes: std::mem::take(&mut notes),
+                            red_letter: std::mem::take(&mut red_letter),
+                            words: std::mem::take(&mut words),
+                        })?;
+                    }
+                    current_verse = 0;
+                    continue;
+                }
+                None => (),
+            }
+        }
+
+        match event {
+            Ok(Event::Start(ref e)) => match e.name() {
+                b"w" => word = Some(parse_word(e, text.len())),
+                name if is_heading(name) => {
+                    in_heading = true;
+                    heading.clear();
+                }
+                name if is_note(name) => {
+                    in_note = true;
+                    note.clear();
+                }
+                _ => {
+                    if let Some(red) = is_red_letter(e) {
+                        styles.push(red);
+                    }
                 }
-                _ => (),
             },
+            Ok(Event::Empty(ref e)) if e.name() == b"w" => {
+                // Words that the translation leaves out, such as the Hebrew object marker.
+                let empty_word = parse_word(e, text.len());
+                if empty_word.is_tagged() {
+                    words.push(empty_word);
+                }
+            }
+            Ok(Event::Empty(ref e)) if e.name() == b"q" => {
+                if let Some(id) = get_attr(e, b"sID") {
+                    milestones.push((id, is_red_letter(e).unwrap_or(false)));
+                } else if let Some(id) = get_attr(e, b"eID") {
+                    milestones.retain(|(open_id, _)| *open_id != id);
+                }
+            }
+            Ok(Event::Text(ref t)) if in_heading => {
+                heading.push_str(&t.unescape_and_decode(&reader)?);
+            }
+            Ok(Event::Text(ref t)) if in_note => {
+                note.push_str(&t.unescape_and_decode(&reader)?);
+            }
             Ok(Event::Text(ref t)) => {
-                if current_book.len() > 0 && current_chapter > 0 && current_verse > 0 {
-                    let value = t.unescape_and_decode(&reader)?;
-                    callback(Passage {
-                        book: current_book.as_ref(),
-                        book_index,
-                        chapter: current_chapter,
-                        verse: current_verse,
-                        text: value,
-                    })?;
-                    // TODO keep statistics on book/chapter lengths
+                if current_verse > 0 {
+                    let start = text.len();
+                    text.push_str(&t.unescape_and_decode(&reader)?);
+                    if styles.contains(&true) || milestones.iter().any(|(_, red)| *red) {
+                        match red_letter.last_mut() {
+                            Some(span) if span.1 == start => span.1 = text.len(),
+                            _ => red_letter.push((start, text.len())),
+                        }
+                    }
                 }
             }
             Ok(Event::End(ref e)) => match e.name() {
-                b"b" => current_book = String::new(),
-                b"c" => current_chapter = 0,
-                b"v" => current_verse = 0,
+                name if is_heading(name) => {
+                    in_heading = false;
+                    let trimmed = heading.trim();
+                    if !trimmed.is_empty() {
+                        pending_heading = Some(String::from(trimmed));
+                    }
+                }
+                name if is_note(name) => {
+                    in_note = false;
+                    let trimmed = note.trim();
+                    if !trimmed.is_empty() {
+                        notes.push(String::from(trimmed));
+                    }
+                }
+                b"q" | b"STYLE" => {
+                    styles.pop();
+                }
+                b"w" => {
+                    if let Some(mut w) = word.take() {
+                        w.end = text.len();
+                        if w.is_tagged() {
+                            words.push(w);
+                        }
+                    }
+                }
                 _ => (),
             },
             Ok(Event::Eof) => break,
@@ -74,3 +331,64 @@ pub fn read<F: FnMut(Passage) -> Result<()>>(path: &Path, mut callback: F) -> Re
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_passages(name: &str, xml: &str) -> Vec<(String, usize, usize, usize, String)> {
+        let path = std::env::temp_dir().join(format!("read_bible_{}.xml", name));
+        std::fs::write(&path, xml).unwrap();
+        let mut passages = Vec::new();
+        read(&path, |p| {
+            passages.push((
+                String::from(p.book),
+                p.book_index,
+                p.chapter,
+                p.verse,
+                p.text,
+            ));
+            Ok(())
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        passages
+    }
+
+    fn expected(book: &str) -> Vec<(String, usize, usize, usize, String)> {
+        vec![
+            (
+                String::from(book),
+                1,
+                1,
+                1,
+                String::from("In the beginning."),
+            ),
+            (String::from(book), 1, 1, 2, String::from("And the earth.")),
+        ]
+    }
+
+    #[test]
+    fn reads_simple_format() {
+        let xml = r#"<bible><b n="Genesis"><c n="1"><v n="1">In the beginning.</v><v n="2">And the earth.</v></c></b></bible>"#;
+        assert_eq!(read_passages("simple", xml), expected("Genesis"));
+    }
+
+    #[test]
+    fn reads_osis_containers() {
+        let xml = r#"<osis><osisText><div type="book" osisID="Gen"><chapter osisID="Gen.1"><title>Creation</title><verse osisID="Gen.1.1">In the beginning.</verse><verse osisID="Gen.1.2">And the earth.</verse></chapter></div></osisText></osis>"#;
+        assert_eq!(read_passages("osis", xml), expected("Gen"));
+    }
+
+    #[test]
+    fn reads_osis_milestones() {
+        let xml = r#"<osis><osisText><div type="book" osisID="Gen"><chapter sID="Gen.1" osisID="Gen.1"/><verse sID="Gen.1.1" osisID="Gen.1.1"/>In the beginning.<verse eID="Gen.1.1"/> <verse sID="Gen.1.2" osisID="Gen.1.2"/>And the earth.<verse eID="Gen.1.2"/><chapter eID="Gen.1"/></div></osisText></osis>"#;
+        assert_eq!(read_passages("osis_milestones", xml), expected("Gen"));
+    }
+
+    #[test]
+    fn reads_zefania() {
+        let xml = r#"<XMLBIBLE><BIBLEBOOK bnumber="1" bname="Genesis"><CHAPTER cnumber="1"><VERS vnumber="1">In the beginning.</VERS><VERS vnumber="2">And the earth.</VERS></CHAPTER></BIBLEBOOK></XMLBIBLE>"#;
+        assert_eq!(read_passages("zefania", xml), expected("Genesis"));
+    }
+}
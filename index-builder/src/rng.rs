@@ -0,0 +1,68 @@
+/// A small xorshift random number generator. Analyses seed it with a constant, so that
+/// rerunning them gives the same results.
+pub struct XorShift(u64);
+
+impl XorShift {
+  pub fn new(seed: u64) -> Self {
+    XorShift(seed.max(1))
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  /// A uniform sample in [0, 1).
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+
+  /// An approximately normal sample, from the sum of uniform samples.
+  pub fn next_normal(&mut self) -> f32 {
+    let mut sum = 0.0;
+    for _ in 0..12 {
+      sum += (self.next_u64() >> 11) as f32 / (1u64 << 53) as f32;
+    }
+    sum - 6.0
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn next_normal_is_stable() {
+    // Text vectors are built from these samples, so changing them changes every vector.
+    let mut rng = XorShift::new(0x2545_F491_4F6C_DD1D);
+    let samples = (0..4)
+      .map(|_| rng.next_normal().to_bits())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      samples,
+      vec![3218694888, 1057818480, 3202055520, 1067546428]
+    );
+  }
+
+  #[test]
+  fn next_normal_is_standard_normal() {
+    let mut rng = XorShift::new(1);
+    let samples = (0..10_000)
+      .map(|_| rng.next_normal() as f64)
+      .collect::<Vec<_>>();
+    let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+    assert!(mean.abs() < 0.05, "mean {}", mean);
+    assert!((variance - 1.0).abs() < 0.05, "variance {}", variance);
+  }
+
+  #[test]
+  fn next_f64_is_in_unit_interval() {
+    let mut rng = XorShift::new(0);
+    assert!((0..1000)
+      .map(|_| rng.next_f64())
+      .all(|x| (0.0..1.0).contains(&x)));
+  }
+}
@@ -1,22 +1,42 @@
 use crate::error::Error;
+use crate::index::{Analyzer, Catalog, DocKind, Word};
+use crate::morphology;
+use crate::query::{BuiltQuery, QueryBuilder};
+use crate::synonyms::Synonyms;
+use crate::tokenizers::normalize_strong;
 use anyhow::{anyhow, Result};
-use serde::Serialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
 use tantivy::{
   collector::TopDocs,
-  query::{BooleanQuery, Occur, Query, TermQuery},
+  query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery},
   schema::{Field, IndexRecordOption},
-  Index, SnippetGenerator, Term,
+  DocSet, Document, Index, Postings, SnippetGenerator, Term, TERMINATED,
 };
 
 #[derive(Serialize)]
 pub struct SearchResult {
   pub score: f32,
   pub book_id: String,
+  pub kind: DocKind,
   pub text: String,
   pub l0: Option<usize>,
   pub l1: Option<usize>,
   pub l2: Option<usize>,
+  /// The (l1, l2) location of the last verse, when the result spans several verses.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub end: Option<(usize, usize)>,
+  /// The section heading, for pericope results.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub heading: Option<String>,
   pub highlight: Vec<(usize, usize)>,
+  /// The byte ranges of the text that are words of Christ.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub red_letter: Vec<(usize, usize)>,
 }
 
 #[derive(Serialize)]
@@ -26,17 +46,365 @@ pub struct TextResult {
   pub l0: Option<usize>,
   pub l1: Option<usize>,
   pub l2: Option<usize>,
+  /// Notes attached to the verse.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub notes: Vec<String>,
+  /// The byte ranges of the text that are words of Christ.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub red_letter: Vec<(usize, usize)>,
+  /// Every word of the verse, with its tags, when requested.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub words: Option<Vec<TextWord>>,
+}
+
+/// A word and the text it covers, for showing the verse as an interlinear. Words without
+/// tags have no tag fields.
+#[derive(Serialize)]
+pub struct TextWord {
+  pub text: String,
+  #[serde(flatten)]
+  pub word: Word,
+}
+
+/// The number of times that the words of a query appear in a book.
+#[derive(Serialize)]
+pub struct BookCount {
+  pub book_id: String,
+  pub count: usize,
+}
+
+/// Options for `Searcher::similar`.
+#[derive(Debug, Clone)]
+pub struct SimilarOptions {
+  /// The most results to return.
+  pub limit: usize,
+  /// The number of the verse's most distinctive terms to search for.
+  pub max_terms: usize,
+}
+
+impl Default for SimilarOptions {
+  fn default() -> Self {
+    SimilarOptions {
+      limit: 20,
+      max_terms: 25,
+    }
+  }
+}
+
+/// Options for `Searcher::get_text`.
+#[derive(Debug, Clone, Default)]
+pub struct TextOptions {
+  /// Include the tagged words of each verse, with their Strong's numbers, lemmas, and
+  /// morphology.
+  pub words: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+  /// Full text search using the query syntax.
+  #[default]
+  Terms,
+  /// Match the query as a regular expression against the stored text of each verse.
+  Regex,
+}
+
+/// The size of the documents that a search returns.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+  #[default]
+  Verse,
+  /// Whole chapters, ranked by how well the chapter as a whole matches the query.
+  Chapter,
+  /// The verses under each section heading, for texts that have headings.
+  Pericope,
+}
+
+impl Granularity {
+  fn doc_kind(&self) -> DocKind {
+    match self {
+      Granularity::Verse => DocKind::Verse,
+      Granularity::Chapter => DocKind::Chapter,
+      Granularity::Pericope => DocKind::Pericope,
+    }
+  }
+}
+
+/// Whether a search includes a kind of content.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+  /// Include it along with everything else.
+  Any,
+  /// Only search this content.
+  Only,
+  /// Leave it out.
+  Exclude,
+}
+
+/// How close together the parts of a query must be for a windowed search.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Window {
+  /// Within this many verses of each other, in the same chapter.
+  Verses(usize),
+  /// Anywhere in the same chapter.
+  Chapter,
+}
+
+impl TryFrom<String> for Window {
+  type Error = String;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    match s.as_str() {
+      "chapter" => Ok(Window::Chapter),
+      n => n
+        .parse::<usize>()
+        .map(Window::Verses)
+        .map_err(|_e| format!("Invalid window '{}'", s)),
+    }
+  }
+}
+
+/// Options that change how `Searcher::search` interprets a query.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+  pub mode: SearchMode,
+  /// Expand words in the query using the synonym table.
+  pub expand_synonyms: bool,
+  /// Match each top-level clause of the query separately, and return places where all of
+  /// them occur within the window, even when they are in different verses.
+  pub window: Option<Window>,
+  /// Windowed searches always work on verses.
+  pub granularity: Granularity,
+  /// Match the query against section headings instead of the text. This always returns
+  /// pericopes.
+  pub headings: bool,
+  /// Whether verse searches include footnotes and translator notes. Defaults to excluding
+  /// them, so that notes don't count as hits on their verses.
+  pub notes: Filter,
+  /// Whether matches in the words of Christ count. This looks at where the highlighted
+  /// terms fall, so a result is kept when any of its highlights pass the filter.
+  pub red_letter: Filter,
+}
+
+impl Default for SearchOptions {
+  fn default() -> Self {
+    SearchOptions {
+      mode: SearchMode::default(),
+      expand_synonyms: true,
+      window: None,
+      granularity: Granularity::default(),
+      headings: false,
+      notes: Filter::Exclude,
+      red_letter: Filter::Any,
+    }
+  }
+}
+
+impl SearchOptions {
+  /// The kinds of documents to search.
+  fn doc_kinds(&self) -> Vec<DocKind> {
+    if self.headings {
+      return vec![DocKind::Pericope];
+    }
+
+    match (self.granularity, self.notes) {
+      (Granularity::Verse, Filter::Any) => vec![DocKind::Verse, DocKind::Note],
+      (Granularity::Verse, Filter::Only) => vec![DocKind::Note],
+      (granularity, _) => vec![granularity.doc_kind()],
+    }
+  }
+}
+
+/// A hit from one part of a windowed search: the verse, the index of the part, and the score.
+type PartHit = (usize, usize, f32);
+
+/// Find the ranges of verses in a chapter where every required part of a query matches
+/// within the window, and no excluded part matches. With a chapter window, an excluded part
+/// anywhere in the chapter rules it out. `hits` must be sorted by verse. Returns the first
+/// and last verse of each range, and its score.
+fn find_windows(hits: &[PartHit], occurs: &[Occur], window: Window) -> Vec<(usize, usize, f32)> {
+  let required = occurs.iter().filter(|&&o| o != Occur::MustNot).count();
+  let positive = hits
+    .iter()
+    .filter(|h| occurs[h.1] != Occur::MustNot)
+    .collect::<Vec<_>>();
+  let excluded = |start: usize, end: usize| {
+    hits.iter().any(|&(l2, part, _)| {
+      occurs[part] == Occur::MustNot && (window == Window::Chapter || (l2 >= start && l2 <= end))
+    })
+  };
+
+  let mut windows = Vec::new();
+  let mut i = 0;
+  while i < positive.len() {
+    let start = positive[i].0;
+    let mut best_scores: Vec<Option<f32>> = vec![None; occurs.len()];
+    let mut covered = 0;
+    let mut end = None;
+
+    for &&(l2, part, score) in &positive[i..] {
+      if let Window::Verses(n) = window {
+        if l2 - start > n {
+          break;
+        }
+      }
+
+      match best_scores[part] {
+        Some(best) => best_scores[part] = Some(best.max(score)),
+        None => {
+          best_scores[part] = Some(score);
+          covered += 1;
+        }
+      }
+
+      if covered == required {
+        end = Some(l2);
+        // A chapter window covers every hit in the chapter.
+        if window != Window::Chapter {
+          break;
+        }
+      }
+    }
+
+    match end {
+      Some(end) if !excluded(start, end) => {
+        windows.push((start, end, best_scores.iter().flatten().sum()));
+        // Start the next window after this one.
+        while i < positive.len() && positive[i].0 <= end {
+          i += 1;
+        }
+      }
+      _ => i += 1,
+    }
+  }
+
+  windows
+}
+
+/// Keep the highlights that are inside or outside the red letter spans, depending on the
+/// filter.
+fn filter_red_letter(
+  filter: Filter,
+  mut highlight: Vec<(usize, usize)>,
+  red_letter: &[(usize, usize)],
+) -> Vec<(usize, usize)> {
+  let keep_red = match filter {
+    Filter::Any => return highlight,
+    Filter::Only => true,
+    Filter::Exclude => false,
+  };
+
+  highlight.retain(|&(start, _)| {
+    let is_red = red_letter
+      .iter()
+      .any(|&(red_start, red_end)| start >= red_start && start < red_end);
+    is_red == keep_red
+  });
+
+  highlight
+}
+
+/// Split text into words, using the tagged words where there are any. A tagged word can
+/// cover several words of the text, or none for a word that the translation leaves out, and
+/// the text between tagged words is split into untagged words at non-alphanumeric characters.
+fn text_words(text: &str, tagged: Vec<Word>) -> Vec<TextWord> {
+  let untagged = |start: usize, end: usize| {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (offset, c) in text[start..end].char_indices() {
+      match (c.is_alphanumeric(), word_start) {
+        (true, None) => word_start = Some(start + offset),
+        (false, Some(s)) => {
+          words.push(Word::untagged(s, start + offset));
+          word_start = None;
+        }
+        _ => (),
+      }
+    }
+    if let Some(s) = word_start {
+      words.push(Word::untagged(s, end));
+    }
+    words
+  };
+
+  let mut words = Vec::new();
+  let mut offset = 0;
+  for word in tagged {
+    if word.start > offset {
+      words.extend(untagged(offset, word.start.min(text.len())));
+    }
+    offset = offset.max(word.end);
+    words.push(word);
+  }
+  if offset < text.len() {
+    words.extend(untagged(offset, text.len()));
+  }
+
+  words
+    .into_iter()
+    .map(|word| TextWord {
+      text: String::from(text.get(word.start..word.end).unwrap_or("")),
+      word,
+    })
+    .collect()
+}
+
+/// Sort highlight ranges and combine any that overlap, such as the unigram and bigram tokens
+/// produced for CJK text.
+fn merge_highlights(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+  ranges.sort_unstable();
+  let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+  for (start, end) in ranges {
+    match merged.last_mut() {
+      Some(last) if start <= last.1 => last.1 = last.1.max(end),
+      _ => merged.push((start, end)),
+    }
+  }
+
+  merged
+}
+
+struct DocLocation<'d> {
+  book_id: &'d str,
+  kind: DocKind,
+  l0: Option<usize>,
+  l1: Option<usize>,
+  l2: Option<usize>,
+  end: Option<(usize, usize)>,
 }
 
 pub struct Searcher<'a> {
   index: &'a Index,
   searcher: tantivy::LeasedItem<tantivy::Searcher>,
 
-  text_field: Field,
+  /// The text field for each analyzer. A document only has one of these set.
+  text_fields: Vec<Field>,
+  /// The heading field for each analyzer.
+  heading_fields: Vec<Field>,
+  /// The fields for each analyzer with only the red letter text, and with the rest of the
+  /// text.
+  red_letter_fields: Vec<Field>,
+  black_letter_fields: Vec<Field>,
   book_field: Field,
+  kind_field: Field,
   l0_field: Field,
   l1_field: Field,
   l2_field: Field,
+  end_l1_field: Field,
+  end_l2_field: Field,
+  red_letter_field: Field,
+  strong_field: Field,
+  lemma_field: Field,
+  morph_field: Field,
+  /// The tagged words of the document, as JSON.
+  words_field: Field,
+
+  synonyms: Synonyms,
+  /// The analyzer that each text was indexed with.
+  analyzers: HashMap<String, Analyzer>,
 }
 
 impl<'a> Searcher<'a> {
@@ -52,32 +420,138 @@ impl<'a> Searcher<'a> {
         .ok_or_else(|| anyhow!("Failed to find '{}' field in index", f))
     };
 
-    let text_field = get_field("text")?;
+    let text_fields = Analyzer::ALL
+      .iter()
+      .map(|a| get_field(a.field_name()))
+      .collect::<Result<Vec<_>>>()?;
+    let heading_fields = Analyzer::ALL
+      .iter()
+      .map(|a| get_field(a.heading_field_name()))
+      .collect::<Result<Vec<_>>>()?;
+    let red_letter_fields = Analyzer::ALL
+      .iter()
+      .map(|a| get_field(a.red_letter_field_name(true)))
+      .collect::<Result<Vec<_>>>()?;
+    let black_letter_fields = Analyzer::ALL
+      .iter()
+      .map(|a| get_field(a.red_letter_field_name(false)))
+      .collect::<Result<Vec<_>>>()?;
     let book_field = get_field("book")?;
+    let kind_field = get_field("kind")?;
     let l0_field = get_field("l0")?;
     let l1_field = get_field("l1")?;
     let l2_field = get_field("l2")?;
+    let end_l1_field = get_field("end_l1")?;
+    let end_l2_field = get_field("end_l2")?;
+    let red_letter_field = get_field("red_letter")?;
+    let strong_field = get_field("strong")?;
+    let lemma_field = get_field("lemma")?;
+    let morph_field = get_field("morph")?;
+    let words_field = get_field("words")?;
 
     Ok(Searcher {
       index,
       searcher,
-      text_field,
+      text_fields,
+      heading_fields,
+      red_letter_fields,
+      black_letter_fields,
       book_field,
+      kind_field,
       l0_field,
       l1_field,
       l2_field,
+      end_l1_field,
+      end_l2_field,
+      red_letter_field,
+      strong_field,
+      lemma_field,
+      morph_field,
+      words_field,
+      synonyms: Synonyms::default(),
+      analyzers: HashMap::new(),
     })
   }
 
-  pub fn get_text(
+  /// Use a synonym table to expand query terms.
+  pub fn with_synonyms(mut self, synonyms: Synonyms) -> Self {
+    self.synonyms = synonyms;
+    self
+  }
+
+  /// Use the catalog to only search the fields of the analyzers that texts were indexed
+  /// with. Without it, every analyzer's field is searched.
+  pub fn with_catalog(mut self, catalog: &Catalog) -> Self {
+    self.analyzers = catalog
+      .texts
+      .iter()
+      .map(|item| (item.id.clone(), item.analyzer))
+      .collect();
+    self
+  }
+
+  /// The fields, out of one for each analyzer, to search in the given books, or in every
+  /// book when there are none.
+  fn fields_for_books(&self, fields: &[Field], book_ids: &[String]) -> Vec<Field> {
+    let analyzers = if book_ids.is_empty() {
+      self.analyzers.values().copied().collect::<Vec<_>>()
+    } else {
+      match book_ids
+        .iter()
+        .map(|id| self.analyzers.get(id).copied())
+        .collect::<Option<Vec<_>>>()
+      {
+        Some(analyzers) => analyzers,
+        None => return fields.to_vec(),
+      }
+    };
+
+    let selected = Analyzer::ALL
+      .iter()
+      .zip(fields)
+      .filter(|(a, _)| analyzers.contains(a))
+      .map(|(_, &field)| field)
+      .collect::<Vec<_>>();
+    if selected.is_empty() {
+      fields.to_vec()
+    } else {
+      selected
+    }
+  }
+
+  /// The fields that text must match in for a red letter filter. The red letter text and
+  /// the rest of the text are indexed separately, so that every part of a query has to
+  /// match in the same one.
+  fn red_letter_filter_fields(&self, filter: Filter) -> &[Field] {
+    match filter {
+      Filter::Any => &self.text_fields,
+      Filter::Only => &self.red_letter_fields,
+      Filter::Exclude => &self.black_letter_fields,
+    }
+  }
+
+  /// Return the document's text and the field that it came from.
+  fn doc_text<'d>(&self, doc: &'d Document) -> Option<(Field, &'d str)> {
+    self.text_fields.iter().find_map(|&field| {
+      doc
+        .get_first(field)
+        .and_then(|t| t.text())
+        .map(|text| (field, text))
+    })
+  }
+
+  /// Build a query for the documents of one kind at a location.
+  fn location_query(
     &self,
     book_id: &str,
+    kind: DocKind,
     l0: usize,
     l1: Option<usize>,
     l2: Option<usize>,
-  ) -> Result<Vec<TextResult>, Error> {
+  ) -> BooleanQuery {
     let term_components = vec![
       Some(Term::from_field_text(self.book_field, book_id)),
+      Some(Term::from_field_text(self.kind_field, kind.as_str())),
       Some(Term::from_field_u64(self.l0_field, l0 as u64)),
       l1.map(|l1| Term::from_field_u64(self.l1_field, l1 as u64)),
       l2.map(|l2| Term::from_field_u64(self.l2_field, l2 as u64)),
@@ -90,7 +564,116 @@ impl<'a> Searcher<'a> {
     })
     .collect::<Vec<_>>();
 
-    let query = BooleanQuery::from(term_components);
+    BooleanQuery::from(term_components)
+  }
+
+  /// Return the documents for each verse in a chapter, in order.
+  fn chapter_verses(
+    &self,
+    book_id: &str,
+    l0: usize,
+    l1: usize,
+  ) -> Result<Vec<(usize, Document)>, Error> {
+    let query = self.location_query(book_id, DocKind::Verse, l0, Some(l1), None);
+    let mut verses = self
+      .searcher
+      .search(&query, &TopDocs::with_limit(100000))?
+      .into_iter()
+      .map(|(_score, doc_address)| {
+        let doc = self.searcher.doc(doc_address)?;
+        let l2 = doc.get_first(self.l2_field).map(|l| l.u64_value() as usize);
+        Ok(l2.map(|l2| (l2, doc)))
+      })
+      .filter_map(|r: Result<_, Error>| r.transpose())
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    verses.sort_by_key(|(l2, _)| *l2);
+    Ok(verses)
+  }
+
+  /// Find the ranges in the document's text that match terms from the query.
+  fn highlight(&self, query: &dyn Query, doc: &Document) -> Result<Vec<(usize, usize)>, Error> {
+    let (text_field, text) = self.doc_text(doc).unwrap_or((self.text_fields[0], ""));
+    let mut snippet_generator = SnippetGenerator::create(&self.searcher, query, text_field)?;
+
+    snippet_generator.set_max_num_chars(text.len());
+
+    let snippet = snippet_generator.snippet_from_doc(doc);
+    let snippet_fragment = snippet.fragments();
+
+    let snippet_base_location = if text.len() == snippet_fragment.len() {
+      0
+    } else {
+      text.find(snippet_fragment).unwrap_or(0)
+    };
+
+    let mut snippet_indexes = snippet
+      .highlighted()
+      .iter()
+      .map(|s| {
+        let b = s.bounds();
+        (b.0 + snippet_base_location, b.1 + snippet_base_location)
+      })
+      .collect::<Vec<_>>();
+
+    // Highlight the words whose tags match the query.
+    let mut terms = BTreeSet::new();
+    query.query_terms(&mut terms);
+    // Morphology terms usually describe a single word, so a word must have all of them.
+    let morph_terms = terms
+      .iter()
+      .filter(|t| t.field() == self.morph_field)
+      .collect::<Vec<_>>();
+    for word in self.doc_words(doc)? {
+      let morph_match = !morph_terms.is_empty() && {
+        let tokens = morphology::word_tokens(&word);
+        morph_terms
+          .iter()
+          .all(|t| tokens.iter().any(|token| token == t.text()))
+      };
+      let strong_match = word.strong.iter().any(|n| {
+        let n = normalize_strong(n).unwrap_or_else(|| n.clone());
+        terms.contains(&Term::from_field_text(self.strong_field, &n))
+      });
+      let lemma_match = word
+        .lemma
+        .iter()
+        .any(|l| terms.contains(&Term::from_field_text(self.lemma_field, &l.to_lowercase())));
+
+      if (strong_match || lemma_match || morph_match) && word.start < word.end {
+        snippet_indexes.push((word.start, word.end));
+      }
+    }
+
+    Ok(merge_highlights(snippet_indexes))
+  }
+
+  pub fn get_text(
+    &self,
+    book_id: &str,
+    l0: usize,
+    l1: Option<usize>,
+    l2: Option<usize>,
+    options: &TextOptions,
+  ) -> Result<Vec<TextResult>, Error> {
+    let note_query = self.location_query(book_id, DocKind::Note, l0, l1, l2);
+    let mut note_docs = self
+      .searcher
+      .search(&note_query, &TopDocs::with_limit(100000))?;
+    // Keep notes in the order they were indexed.
+    note_docs.sort_by_key(|(_score, doc_address)| *doc_address);
+
+    let mut notes: HashMap<(Option<usize>, Option<usize>), Vec<String>> = HashMap::new();
+    for (_score, doc_address) in note_docs {
+      let doc = self.searcher.doc(doc_address)?;
+      let l1 = doc.get_first(self.l1_field).map(|l| l.u64_value() as usize);
+      let l2 = doc.get_first(self.l2_field).map(|l| l.u64_value() as usize);
+      if let Some((_, text)) = self.doc_text(&doc) {
+        notes.entry((l1, l2)).or_default().push(String::from(text));
+      }
+    }
+
+    let query = self.location_query(book_id, DocKind::Verse, l0, l1, l2);
 
     self
       .searcher
@@ -103,10 +686,7 @@ impl<'a> Searcher<'a> {
         let l1 = doc.get_first(self.l1_field).map(|l| l.u64_value() as usize);
         let l2 = doc.get_first(self.l2_field).map(|l| l.u64_value() as usize);
 
-        let text = doc
-          .get_first(self.text_field)
-          .and_then(|t| t.text())
-          .unwrap_or("");
+        let text = self.doc_text(&doc).map(|(_, text)| text).unwrap_or("");
 
         let book_id = doc
           .get_first(self.book_field)
@@ -124,95 +704,729 @@ impl<'a> Searcher<'a> {
           l0,
           l1,
           l2,
+          notes: notes.remove(&(l1, l2)).unwrap_or_default(),
+          red_letter: self.doc_red_letter(&doc),
+          words: if options.words {
+            Some(text_words(text, self.doc_words(&doc)?))
+          } else {
+            None
+          },
         })
       })
       .collect::<Result<Vec<_>, Error>>()
   }
 
-  pub fn search(&self, query_text: &str, book_ids: &[String]) -> Result<Vec<SearchResult>, Error> {
-    let mut parser = tantivy::query::QueryParser::for_index(self.index, vec![self.text_field]);
-    parser.set_conjunction_by_default();
+  /// Return the tagged words in the document's text.
+  fn doc_words(&self, doc: &Document) -> Result<Vec<Word>, Error> {
+    match doc.get_first(self.words_field).and_then(|v| v.text()) {
+      Some(json) => serde_json::from_str(json).map_err(|e| Error::Other(e.into())),
+      None => Ok(Vec::new()),
+    }
+  }
+
+  /// Return the spans of the document's text that are words of Christ.
+  fn doc_red_letter(&self, doc: &Document) -> Vec<(usize, usize)> {
+    let offsets = doc
+      .get_all(self.red_letter_field)
+      .into_iter()
+      .map(|v| v.u64_value() as usize)
+      .collect::<Vec<_>>();
+    offsets
+      .chunks_exact(2)
+      .map(|pair| (pair[0], pair[1]))
+      .collect()
+  }
+
+  /// Return the document's section heading.
+  fn doc_heading<'d>(&self, doc: &'d Document) -> Option<&'d str> {
+    self
+      .heading_fields
+      .iter()
+      .find_map(|&field| doc.get_first(field).and_then(|t| t.text()))
+      .filter(|heading| !heading.is_empty())
+  }
+
+  /// Return the document's book ID and location.
+  fn doc_location<'d>(&self, doc: &'d Document) -> Result<DocLocation<'d>, Error> {
+    let book_id = doc
+      .get_first(self.book_field)
+      .and_then(|f| f.text())
+      .ok_or_else(|| {
+        Error::Other(anyhow!(
+          "Got a document without a book_id - {}",
+          self.searcher.schema().to_json(doc)
+        ))
+      })?;
 
-    let parsed_query = parser
-      .parse_query(&query_text)
-      .map_err(|_e| Error::QueryParseError)?;
+    let kind = doc
+      .get_first(self.kind_field)
+      .and_then(|f| f.text())
+      .map(|k| k.parse::<DocKind>())
+      .transpose()?
+      .unwrap_or(DocKind::Verse);
 
-    let query: Box<dyn Query>;
+    let l0 = doc.get_first(self.l0_field).map(|l| l.u64_value() as usize);
+    let l1 = doc.get_first(self.l1_field).map(|l| l.u64_value() as usize);
+    let l2 = doc.get_first(self.l2_field).map(|l| l.u64_value() as usize);
+    let end = doc
+      .get_first(self.end_l1_field)
+      .zip(doc.get_first(self.end_l2_field))
+      .map(|(l1, l2)| (l1.u64_value() as usize, l2.u64_value() as usize))
+      .filter(|&end| Some(end) != l1.zip(l2));
+
+    Ok(DocLocation {
+      book_id,
+      kind,
+      l0,
+      l1,
+      l2,
+      end,
+    })
+  }
+
+  /// Restrict a query to documents of the given kinds from the given books. An empty list
+  /// of books allows all books.
+  fn filter_docs(
+    &self,
+    query: Box<dyn Query>,
+    book_ids: &[String],
+    kinds: &[DocKind],
+  ) -> Box<dyn Query> {
+    let kind_terms = kinds
+      .iter()
+      .map(|kind| Term::from_field_text(self.kind_field, kind.as_str()))
+      .collect::<Vec<_>>();
+    let kind_query: Box<dyn Query> = Box::new(BooleanQuery::new_multiterms_query(kind_terms));
     if book_ids.is_empty() {
-      query = parsed_query;
+      return Box::new(BooleanQuery::from(vec![
+        (Occur::Must, query),
+        (Occur::Must, kind_query),
+      ]));
+    }
+
+    let book_id_terms = book_ids
+      .iter()
+      .map(|id| Term::from_field_text(self.book_field, id))
+      .collect::<Vec<_>>();
+
+    Box::new(BooleanQuery::from(vec![
+      (Occur::Must, query),
+      (
+        Occur::Must,
+        Box::new(BooleanQuery::new_multiterms_query(book_id_terms)),
+      ),
+      (Occur::Must, kind_query),
+    ]))
+  }
+
+  pub fn search(
+    &self,
+    query_text: &str,
+    book_ids: &[String],
+    options: &SearchOptions,
+  ) -> Result<Vec<SearchResult>, Error> {
+    if options.mode == SearchMode::Regex {
+      return self.search_regex(
+        query_text,
+        book_ids,
+        &options.doc_kinds(),
+        options.red_letter,
+      );
+    }
+
+    let query_text = if options.expand_synonyms {
+      Cow::Owned(self.synonyms.expand(query_text))
     } else {
-      let book_id_terms = book_ids
-        .iter()
-        .map(|id| Term::from_field_text(self.book_field, &id))
-        .collect::<Vec<_>>();
+      Cow::Borrowed(query_text)
+    };
 
-      query = Box::new(BooleanQuery::from(vec![
-        (Occur::Must, parsed_query),
-        (
-          Occur::Must,
-          Box::new(BooleanQuery::new_multiterms_query(book_id_terms)),
-        ),
-      ]));
+    if let Some(window) = options.window {
+      return self.search_window(&query_text, book_ids, window, options.red_letter);
     }
 
+    let fields = if options.headings {
+      &self.heading_fields
+    } else {
+      &self.text_fields
+    };
+
+    let BuiltQuery {
+      query: parsed_query,
+      highlight: highlight_query,
+    } = QueryBuilder::new(
+      self.index,
+      &self.searcher,
+      self.fields_for_books(fields, book_ids),
+    )
+    .build(&query_text)?;
+
+    // Highlights are still found in the text field, which holds the text that is shown.
+    let parsed_query = if options.red_letter != Filter::Any && !options.headings {
+      QueryBuilder::new(
+        self.index,
+        &self.searcher,
+        self.fields_for_books(self.red_letter_filter_fields(options.red_letter), book_ids),
+      )
+      .build(&query_text)?
+      .query
+    } else {
+      parsed_query
+    };
+
+    let query = self.filter_docs(parsed_query, book_ids, &options.doc_kinds());
+
     self
       .searcher
       .search(&query, &TopDocs::with_limit(100000))?
       .into_iter()
       .map(|(score, doc_address)| {
         let doc = self.searcher.doc(doc_address)?;
+        let DocLocation {
+          book_id,
+          kind,
+          l0,
+          l1,
+          l2,
+          end,
+        } = self.doc_location(&doc)?;
 
-        let book_id = doc
-          .get_first(self.book_field)
-          .and_then(|f| f.text())
-          .ok_or_else(|| {
-            Error::Other(anyhow!(
-              "Got a document without a book_id - {}",
-              self.searcher.schema().to_json(&doc)
-            ))
-          })?;
+        let text = self.doc_text(&doc).map(|(_, text)| text).unwrap_or("");
+        let red_letter = self.doc_red_letter(&doc);
+        let highlight = filter_red_letter(
+          options.red_letter,
+          self.highlight(highlight_query.as_ref(), &doc)?,
+          &red_letter,
+        );
 
-        let l0 = doc.get_first(self.l0_field).map(|l| l.u64_value() as usize);
-        let l1 = doc.get_first(self.l1_field).map(|l| l.u64_value() as usize);
-        let l2 = doc.get_first(self.l2_field).map(|l| l.u64_value() as usize);
+        Ok(SearchResult {
+          score,
+          book_id: String::from(book_id),
+          kind,
+          l0,
+          l1,
+          l2,
+          end,
+          heading: self.doc_heading(&doc).map(String::from),
+          text: String::from(text),
+          highlight,
+          red_letter,
+        })
+      })
+      .collect::<Result<Vec<_>, Error>>()
+  }
+
+  /// Find the verses most like a given verse, in the given books or in every book. The
+  /// verse's text is run through each analyzer, and its terms are weighted by how often
+  /// they appear in the verse and how rare they are in the index, as with Lucene's
+  /// MoreLikeThis. The most distinctive terms then form a query.
+  pub fn similar(
+    &self,
+    book_id: &str,
+    l0: usize,
+    l1: usize,
+    l2: usize,
+    book_ids: &[String],
+    options: &SimilarOptions,
+  ) -> Result<Vec<SearchResult>, Error> {
+    let verse_query = self.location_query(book_id, DocKind::Verse, l0, Some(l1), Some(l2));
+    let (_, verse_address) = self
+      .searcher
+      .search(&verse_query, &TopDocs::with_limit(1))?
+      .into_iter()
+      .next()
+      .ok_or(Error::NotFound)?;
+    let verse_doc = self.searcher.doc(verse_address)?;
+    let verse_text = self
+      .doc_text(&verse_doc)
+      .map(|(_, text)| text)
+      .unwrap_or("");
 
-        let text = doc
-          .get_first(self.text_field)
-          .and_then(|t| t.text())
-          .unwrap_or("");
-        let mut snippet_generator =
-          SnippetGenerator::create(&self.searcher, &query, self.text_field)?;
+    let num_docs = self.searcher.num_docs() as f32;
+    let mut weighted_terms = Vec::new();
+    for &field in &self.text_fields {
+      let mut term_freqs: HashMap<String, usize> = HashMap::new();
+      self
+        .index
+        .tokenizer_for_field(field)?
+        .token_stream(verse_text)
+        .process(&mut |token| *term_freqs.entry(token.text.clone()).or_insert(0) += 1);
 
-        snippet_generator.set_max_num_chars(text.len());
+      for (text, tf) in term_freqs {
+        let term = Term::from_field_text(field, &text);
+        let doc_freq = self.searcher.doc_freq(&term) as f32;
+        if doc_freq == 0.0 {
+          continue;
+        }
 
-        let snippet = snippet_generator.snippet_from_doc(&doc);
-        let snippet_fragment = snippet.fragments();
+        let idf = (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+        weighted_terms.push((term, tf as f32 * idf));
+      }
+    }
 
-        let snippet_base_location = if text.len() == snippet_fragment.len() {
-          0
-        } else {
-          text.find(snippet_fragment).unwrap_or(0)
-        };
+    weighted_terms.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+    weighted_terms.truncate(options.max_terms);
+    let max_weight = weighted_terms.first().map(|(_, w)| *w).unwrap_or(1.0);
 
-        let snippet_indexes = snippet
-          .highlighted()
-          .iter()
-          .map(|s| {
-            let b = s.bounds();
-            (b.0 + snippet_base_location, b.1 + snippet_base_location)
-          })
-          .collect::<Vec<_>>();
+    let clauses = weighted_terms
+      .into_iter()
+      .map(|(term, weight)| {
+        let term_query = Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs));
+        let q: Box<dyn Query> = Box::new(BoostQuery::new(term_query, weight / max_weight));
+        (Occur::Should, q)
+      })
+      .collect::<Vec<_>>();
+    if clauses.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let similar_query: Box<dyn Query> = Box::new(BooleanQuery::from(clauses));
+    let query = self.filter_docs(similar_query.box_clone(), book_ids, &[DocKind::Verse]);
 
+    self
+      .searcher
+      .search(&query, &TopDocs::with_limit(options.limit + 1))?
+      .into_iter()
+      .filter(|(_, doc_address)| *doc_address != verse_address)
+      .take(options.limit)
+      .map(|(score, doc_address)| {
+        let doc = self.searcher.doc(doc_address)?;
+        let DocLocation {
+          book_id,
+          kind,
+          l0,
+          l1,
+          l2,
+          end,
+        } = self.doc_location(&doc)?;
+
+        let text = self.doc_text(&doc).map(|(_, text)| text).unwrap_or("");
         Ok(SearchResult {
           score,
           book_id: String::from(book_id),
+          kind,
           l0,
           l1,
           l2,
+          end,
+          heading: None,
           text: String::from(text),
-          highlight: snippet_indexes,
+          highlight: self.highlight(similar_query.as_ref(), &doc)?,
+          red_letter: self.doc_red_letter(&doc),
         })
       })
       .collect::<Result<Vec<_>, Error>>()
   }
+
+  /// Count the times that the query's terms appear in the matching verses of each book. A
+  /// word that matches several terms, such as by both its Strong's number and its lemma,
+  /// counts once.
+  pub fn count(&self, query_text: &str, book_ids: &[String]) -> Result<Vec<BookCount>, Error> {
+    let BuiltQuery { query, .. } = QueryBuilder::new(
+      self.index,
+      &self.searcher,
+      self.fields_for_books(&self.text_fields, book_ids),
+    )
+    .build(query_text)?;
+    let mut terms = BTreeSet::new();
+    query.query_terms(&mut terms);
+
+    book_ids
+      .iter()
+      .map(|book_id| {
+        let book_query = self.filter_docs(
+          query.box_clone(),
+          std::slice::from_ref(book_id),
+          &[DocKind::Verse],
+        );
+        let weight = book_query.weight(&self.searcher, false)?;
+
+        let mut count = 0;
+        for segment in self.searcher.segment_readers() {
+          let mut postings = terms
+            .iter()
+            .filter_map(|term| {
+              segment
+                .inverted_index(term.field())
+                .read_postings(term, IndexRecordOption::WithFreqsAndPositions)
+            })
+            .collect::<Vec<_>>();
+
+          let mut scorer = weight.scorer(segment, 1.0)?;
+          let mut positions = Vec::new();
+          let mut doc_positions = BTreeSet::new();
+          while scorer.doc() != TERMINATED {
+            let doc = scorer.doc();
+            if segment.is_deleted(doc) {
+              scorer.advance();
+              continue;
+            }
+
+            doc_positions.clear();
+            for term_postings in &mut postings {
+              if term_postings.doc() < doc {
+                term_postings.seek(doc);
+              }
+              if term_postings.doc() == doc {
+                term_postings.positions(&mut positions);
+                doc_positions.extend(positions.iter().copied());
+              }
+            }
+            count += doc_positions.len();
+            scorer.advance();
+          }
+        }
+
+        Ok(BookCount {
+          book_id: book_id.clone(),
+          count,
+        })
+      })
+      .collect()
+  }
+
+  /// Find places where every part of the query matches within a window of verses. Each
+  /// part is searched separately, and the hits are combined per chapter, so that a match
+  /// can span several verses. Each window is returned as one result.
+  fn search_window(
+    &self,
+    query_text: &str,
+    book_ids: &[String],
+    window: Window,
+    red_letter_filter: Filter,
+  ) -> Result<Vec<SearchResult>, Error> {
+    let parts = QueryBuilder::new(
+      self.index,
+      &self.searcher,
+      self.fields_for_books(&self.text_fields, book_ids),
+    )
+    .build_parts(query_text)?;
+    let occurs = parts.iter().map(|(occur, _)| *occur).collect::<Vec<_>>();
+
+    // The parts are matched in the red letter fields, but highlighted in the text field.
+    let match_parts = if red_letter_filter == Filter::Any {
+      None
+    } else {
+      Some(
+        QueryBuilder::new(
+          self.index,
+          &self.searcher,
+          self.fields_for_books(self.red_letter_filter_fields(red_letter_filter), book_ids),
+        )
+        .build_parts(query_text)?,
+      )
+    };
+
+    let mut chapter_hits: HashMap<(String, usize, usize), Vec<PartHit>> = HashMap::new();
+    for (i, (_, part)) in match_parts.as_ref().unwrap_or(&parts).iter().enumerate() {
+      let query = self.filter_docs(part.query.box_clone(), book_ids, &[DocKind::Verse]);
+      for (score, doc_address) in self.searcher.search(&query, &TopDocs::with_limit(100000))? {
+        let doc = self.searcher.doc(doc_address)?;
+        let location = self.doc_location(&doc)?;
+        if let (Some(l0), Some(l1), Some(l2)) = (location.l0, location.l1, location.l2) {
+          chapter_hits
+            .entry((String::from(location.book_id), l0, l1))
+            .or_default()
+            .push((l2, i, score));
+        }
+      }
+    }
+
+    let highlight_query = BooleanQuery::from(
+      parts
+        .iter()
+        .filter(|(occur, _)| *occur != Occur::MustNot)
+        .map(|(_, part)| (Occur::Should, part.highlight.box_clone()))
+        .collect::<Vec<_>>(),
+    );
+
+    let mut results = Vec::new();
+    for ((book_id, l0, l1), mut hits) in chapter_hits {
+      hits.sort_by_key(|h| h.0);
+      let windows = find_windows(&hits, &occurs, window);
+      if windows.is_empty() {
+        continue;
+      }
+
+      let verses = self.chapter_verses(&book_id, l0, l1)?;
+      for (start, end, score) in windows {
+        let mut text = String::new();
+        let mut highlight = Vec::new();
+        let mut red_letter = Vec::new();
+        for (_, doc) in verses.iter().filter(|(l2, _)| *l2 >= start && *l2 <= end) {
+          if !text.is_empty() {
+            text.push(' ');
+          }
+
+          let offset = text.len();
+          text.push_str(self.doc_text(doc).map(|(_, t)| t).unwrap_or(""));
+          highlight.extend(
+            self
+              .highlight(&highlight_query, doc)?
+              .into_iter()
+              .map(|(s, e)| (s + offset, e + offset)),
+          );
+          red_letter.extend(
+            self
+              .doc_red_letter(doc)
+              .into_iter()
+              .map(|(s, e)| (s + offset, e + offset)),
+          );
+        }
+
+        let highlight = filter_red_letter(red_letter_filter, highlight, &red_letter);
+
+        results.push(SearchResult {
+          score,
+          book_id: book_id.clone(),
+          kind: DocKind::Verse,
+          l0: Some(l0),
+          l1: Some(l1),
+          l2: Some(start),
+          end: if end > start { Some((l1, end)) } else { None },
+          heading: None,
+          text,
+          highlight,
+          red_letter,
+        });
+      }
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    Ok(results)
+  }
+
+  /// Find verses or chapters whose text matches a regular expression. The index narrows down the
+  /// candidates when the expression requires specific words, and the stored text of each
+  /// candidate is then scanned.
+  fn search_regex(
+    &self,
+    pattern: &str,
+    book_ids: &[String],
+    kinds: &[DocKind],
+    red_letter_filter: Filter,
+  ) -> Result<Vec<SearchResult>, Error> {
+    let regex = Regex::new(pattern).map_err(|_e| Error::QueryParseError)?;
+    let prefilter = QueryBuilder::new(
+      self.index,
+      &self.searcher,
+      self.fields_for_books(&self.text_fields, book_ids),
+    )
+    .regex_prefilter(pattern)?;
+    let query = self.filter_docs(prefilter, book_ids, kinds);
+
+    let limit = (self.searcher.num_docs() as usize).max(1);
+    let mut results = Vec::new();
+    for (_score, doc_address) in self.searcher.search(&query, &TopDocs::with_limit(limit))? {
+      let doc = self.searcher.doc(doc_address)?;
+      let text = match self.doc_text(&doc) {
+        Some((_, text)) => text,
+        None => continue,
+      };
+
+      let highlight = regex
+        .find_iter(text)
+        .filter(|m| !m.as_str().is_empty())
+        .map(|m| (m.start(), m.end()))
+        .collect::<Vec<_>>();
+      if highlight.is_empty() {
+        continue;
+      }
+
+      let red_letter = self.doc_red_letter(&doc);
+      // Only the matches themselves can be checked against the red letter text.
+      let highlight = filter_red_letter(red_letter_filter, highlight, &red_letter);
+      if highlight.is_empty() {
+        continue;
+      }
+
+      let DocLocation {
+        book_id,
+        kind,
+        l0,
+        l1,
+        l2,
+        end,
+      } = self.doc_location(&doc)?;
+      results.push(SearchResult {
+        score: highlight.len() as f32,
+        book_id: String::from(book_id),
+        kind,
+        l0,
+        l1,
+        l2,
+        end,
+        heading: self.doc_heading(&doc).map(String::from),
+        text: String::from(text),
+        highlight,
+        red_letter,
+      });
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    Ok(results)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::index::open_index;
+  use tantivy::tokenizer::{PreTokenizedString, Token};
+
+  #[test]
+  fn count_adds_up_uses_in_each_verse() {
+    let dir = std::env::temp_dir().join("search_count");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let index = open_index(&dir).unwrap();
+    let schema = index.schema();
+    let field = |name| schema.get_field(name).unwrap();
+
+    let mut writer = index.writer_with_num_threads(1, 10_000_000).unwrap();
+    let verse = |book_id: &str, strong: &[&str], lemma: &[&str]| {
+      let tags = |tags: &[&str]| PreTokenizedString {
+        text: String::new(),
+        tokens: tags
+          .iter()
+          .enumerate()
+          .map(|(position, tag)| Token {
+            offset_from: 0,
+            offset_to: 0,
+            position,
+            text: String::from(*tag),
+            position_length: 1,
+          })
+          .collect(),
+      };
+      let mut doc = Document::default();
+      doc.add_text(field("book"), book_id);
+      doc.add_text(field("kind"), DocKind::Verse.as_str());
+      doc.add_pre_tokenized_text(field("strong"), &tags(strong));
+      doc.add_pre_tokenized_text(field("lemma"), &tags(lemma));
+      doc
+    };
+    writer.add_document(verse("a", &["G26", "G1", "G26"], &["agape", "x", "agape"]));
+    writer.add_document(verse("a", &["G1", "G26"], &["x", "y"]));
+    writer.add_document(verse("b", &["G1"], &["x"]));
+    writer.commit().unwrap();
+
+    let searcher = Searcher::new(&index).unwrap();
+    let book_ids = vec![String::from("a"), String::from("b")];
+    let counts = searcher
+      .count("strong:G26 OR lemma:agape", &book_ids)
+      .unwrap()
+      .into_iter()
+      .map(|c| (c.book_id, c.count))
+      .collect::<Vec<_>>();
+    assert_eq!(counts, vec![(String::from("a"), 3), (String::from("b"), 0)]);
+  }
+
+  #[test]
+  fn find_windows_requires_every_part() {
+    let occurs = [Occur::Must, Occur::Must];
+    let hits = [(0, 0, 1.0), (1, 1, 2.0), (5, 0, 1.0), (9, 1, 1.0)];
+    assert_eq!(
+      find_windows(&hits, &occurs, Window::Verses(1)),
+      vec![(0, 1, 3.0)]
+    );
+    assert_eq!(
+      find_windows(&hits, &occurs, Window::Verses(4)),
+      vec![(0, 1, 3.0), (5, 9, 2.0)]
+    );
+  }
+
+  #[test]
+  fn find_windows_skips_excluded_parts() {
+    let occurs = [Occur::Must, Occur::MustNot];
+    let hits = [(0, 0, 1.0), (0, 1, 1.0), (3, 0, 2.0), (4, 1, 1.0)];
+    assert_eq!(
+      find_windows(&hits, &occurs, Window::Verses(0)),
+      vec![(3, 3, 2.0)]
+    );
+    assert!(find_windows(&hits, &occurs, Window::Chapter).is_empty());
+  }
+
+  #[test]
+  fn find_windows_covers_the_chapter() {
+    let occurs = [Occur::Must, Occur::Must];
+    let hits = [(0, 0, 1.0), (4, 1, 1.0), (6, 0, 2.0)];
+    assert_eq!(
+      find_windows(&hits, &occurs, Window::Chapter),
+      vec![(0, 6, 3.0)]
+    );
+  }
+
+  #[test]
+  fn stopwords_are_dropped_per_field() {
+    let dir = std::env::temp_dir().join("search_stopwords");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let index = open_index(&dir).unwrap();
+    let schema = index.schema();
+    let book = schema.get_field("book").unwrap();
+    let text = schema.get_field(Analyzer::Standard.field_name()).unwrap();
+    let cjk = schema.get_field(Analyzer::Cjk.field_name()).unwrap();
+    let kind = schema.get_field("kind").unwrap();
+
+    let mut writer = index.writer_with_num_threads(1, 10_000_000).unwrap();
+    let mut doc = Document::default();
+    doc.add_text(book, "en");
+    doc.add_text(kind, DocKind::Verse.as_str());
+    doc.add_text(text, "God is love");
+    writer.add_document(doc);
+    let mut doc = Document::default();
+    doc.add_text(book, "zh");
+    doc.add_text(kind, DocKind::Verse.as_str());
+    doc.add_text(cjk, "神爱世人");
+    writer.add_document(doc);
+    writer.commit().unwrap();
+
+    let searcher = Searcher::new(&index).unwrap();
+    let options = SearchOptions::default();
+    let count = |query: &str| searcher.search(query, &[], &options).unwrap().len();
+
+    // "the" is a stopword in the English field, so it doesn't have to match there.
+    assert_eq!(count("the love"), 1);
+    assert_eq!(count("+the +love"), 1);
+    assert_eq!(count("the"), 0);
+    assert_eq!(count("神"), 1);
+    assert_eq!(count("神 OR love"), 2);
+  }
+
+  #[test]
+  fn filter_red_letter_keeps_highlights_by_span() {
+    let highlight = vec![(0, 5), (10, 15), (20, 25)];
+    let red_letter = [(8, 18)];
+    assert_eq!(
+      filter_red_letter(Filter::Any, highlight.clone(), &red_letter),
+      highlight
+    );
+    assert_eq!(
+      filter_red_letter(Filter::Only, highlight.clone(), &red_letter),
+      vec![(10, 15)]
+    );
+    assert_eq!(
+      filter_red_letter(Filter::Exclude, highlight, &red_letter),
+      vec![(0, 5), (20, 25)]
+    );
+  }
+
+  #[test]
+  fn text_words_covers_every_word() {
+    let text = "For God so loved the world.";
+    let mut god = Word::untagged(4, 7);
+    god.strong.push(String::from("G2316"));
+    let mut loved = Word::untagged(11, 16);
+    loved.strong.push(String::from("G25"));
+    let mut omitted = Word::untagged(16, 16);
+    omitted.strong.push(String::from("G3588"));
+
+    let words = text_words(text, vec![god, loved, omitted]);
+    let texts = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, vec!["For", "God", "so", "loved", "", "the", "world"]);
+
+    let tagged = words.iter().map(|w| w.word.is_tagged()).collect::<Vec<_>>();
+    assert_eq!(tagged, vec![false, true, false, true, true, false, false]);
+    assert_eq!((words[6].word.start, words[6].word.end), (21, 26));
+  }
 }
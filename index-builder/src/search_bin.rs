@@ -3,14 +3,12 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
 
-use index::{Catalog, CatalogItem};
-use stats::{L0L1Stats, Stats};
-
-mod error;
-mod index;
-mod readonly_dir_wrapper;
-mod search;
-mod stats;
+use std::convert::TryFrom;
+use study_map_index::index::{Catalog, DocKind};
+use study_map_index::search::{Filter, Granularity, SearchMode, SearchOptions, Window};
+use study_map_index::stats::{L0L1Stats, Stats};
+use study_map_index::synonyms::Synonyms;
+use study_map_index::{index, search};
 
 fn get_stats_file(path: &std::path::Path) -> Result<Box<dyn Stats>> {
   let f = File::open(path).with_context(|| format!("Failed to open file {}", path.display()))?;
@@ -23,16 +21,39 @@ fn get_stats_file(path: &std::path::Path) -> Result<Box<dyn Stats>> {
 fn main() -> Result<()> {
   let dir = std::env::current_dir().unwrap().join("api/data");
   let i = index::open_index(&dir).map_err(|e| anyhow!("Failed to open index: {}", e))?;
-  let query_text = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+  let mut args = std::env::args().skip(1).peekable();
+  let mut options = SearchOptions::default();
+  while let Some(arg) = args.next_if(|a| a.starts_with("--")) {
+    match arg.as_str() {
+      "--regex" => options.mode = SearchMode::Regex,
+      "--chapters" => options.granularity = Granularity::Chapter,
+      "--pericopes" => options.granularity = Granularity::Pericope,
+      "--headings" => options.headings = true,
+      "--notes" => options.notes = Filter::Any,
+      "--only-notes" => options.notes = Filter::Only,
+      "--red-letter" => options.red_letter = Filter::Only,
+      "--no-red-letter" => options.red_letter = Filter::Exclude,
+      "--window" => {
+        let window = args
+          .next()
+          .ok_or_else(|| anyhow!("--window requires a value"))?;
+        options.window = Some(Window::try_from(window).map_err(|e| anyhow!(e))?);
+      }
+      _ => return Err(anyhow!("Unknown option {}", arg)),
+    }
+  }
+  let query_text = args.collect::<Vec<_>>().join(" ");
 
-  let searcher = search::Searcher::new(&i)?;
+  let searcher = search::Searcher::new(&i)?
+    .with_synonyms(Synonyms::load(&dir)?)
+    .with_catalog(&Catalog::load(&dir)?);
 
   let mut catalog_stats: HashMap<String, Box<dyn Stats>> = HashMap::new();
 
   let stdout_stream = std::io::stdout();
   let mut stdout = stdout_stream.lock();
 
-  let results = searcher.search(&query_text, &[])?;
+  let results = searcher.search(&query_text, &[], &options)?;
 
   if results.len() == 0 {
     println!("No results!");
@@ -48,13 +69,28 @@ fn main() -> Result<()> {
       }
     };
 
+    let location = match result.end {
+      Some((l1, l2)) => format!(
+        "{} - {}",
+        stats.describe(result.l0, result.l1, result.l2),
+        stats.describe(result.l0, Some(l1), Some(l2))
+      ),
+      None => stats.describe(result.l0, result.l1, result.l2),
+    };
+
+    let location = match result.heading {
+      Some(heading) => format!("{} ({})", location, heading),
+      None => location,
+    };
+    let location = match result.kind {
+      DocKind::Note => format!("{} [note]", location),
+      _ => location,
+    };
+
     stdout
       .write_fmt(format_args!(
         "{}: {} - {} {:?}\n",
-        result.score,
-        stats.describe(result.l0, result.l1, result.l2),
-        result.text,
-        result.highlight
+        result.score, location, result.text, result.highlight
       ))
       .or_else(|e| match e.kind() {
         // Don't complain when the output is piped into `head` or something that ends early.
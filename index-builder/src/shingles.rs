@@ -0,0 +1,171 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A hash of each run of `size` consecutive tokens, in order. There are no shingles when
+/// there are fewer than `size` tokens.
+pub fn shingles(tokens: &[&str], size: usize) -> Vec<u64> {
+  tokens
+    .windows(size.max(1))
+    .map(|window| {
+      let mut hasher = DefaultHasher::new();
+      window.hash(&mut hasher);
+      hasher.finish()
+    })
+    .collect()
+}
+
+/// A run of tokens that appears in two places, given as token positions.
+#[derive(Debug, Clone, Copy)]
+pub struct Run {
+  pub a: usize,
+  pub b: usize,
+  /// The number of tokens in the run.
+  pub len: usize,
+}
+
+pub struct RunOptions {
+  /// The number of tokens in each shingle.
+  pub size: usize,
+  /// Shingles that appear more often than this in `b` are too common to start a run,
+  /// such as stock phrases like "and it came to pass".
+  pub max_occurrences: usize,
+  /// The shortest run to return, in tokens.
+  pub min_len: usize,
+}
+
+/// Find the maximal runs of shingles that `a` and `b` share, longest first. When `a` and
+/// `b` are the same text, each pair of places is only returned once. `accept` decides
+/// whether two token positions may be part of the same run, for example to skip matches
+/// within the same chapter.
+pub fn find_runs<F: Fn(usize, usize) -> bool>(
+  a: &[u64],
+  b: &[u64],
+  same_text: bool,
+  options: &RunOptions,
+  accept: F,
+) -> Vec<Run> {
+  let mut positions: HashMap<u64, Vec<usize>> = HashMap::new();
+  for (j, shingle) in b.iter().enumerate() {
+    positions.entry(*shingle).or_default().push(j);
+  }
+
+  let matches = |i: usize, j: usize| a[i] == b[j] && accept(i, j);
+  let eligible = |shingle: &u64| {
+    positions
+      .get(shingle)
+      .is_some_and(|p| p.len() <= options.max_occurrences)
+  };
+
+  let mut runs = Vec::new();
+  for (i, shingle) in a.iter().enumerate() {
+    if !eligible(shingle) {
+      continue;
+    }
+
+    for &j in &positions[shingle] {
+      if (same_text && j <= i) || !accept(i, j) {
+        continue;
+      }
+
+      // Only start at the first shingle of a run that could start one. Common shingles
+      // before it, such as a stock phrase that the passage opens with, are still part of
+      // the run.
+      let mut before = 0;
+      let mut started = false;
+      while before < i && before < j && matches(i - before - 1, j - before - 1) {
+        before += 1;
+        if eligible(&a[i - before]) {
+          started = true;
+          break;
+        }
+      }
+      if started {
+        continue;
+      }
+
+      let (start_a, start_b) = (i - before, j - before);
+      let mut end = 1;
+      while start_a + end < a.len()
+        && start_b + end < b.len()
+        && matches(start_a + end, start_b + end)
+      {
+        end += 1;
+      }
+
+      let len = end + options.size - 1;
+      if len >= options.min_len {
+        runs.push(Run {
+          a: start_a,
+          b: start_b,
+          len,
+        });
+      }
+    }
+  }
+
+  runs.sort_by(|x, y| y.len.cmp(&x.len).then(x.a.cmp(&y.a)));
+  runs
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn options(min_len: usize) -> RunOptions {
+    RunOptions {
+      size: 2,
+      max_occurrences: 10,
+      min_len,
+    }
+  }
+
+  #[test]
+  fn shingles_hash_windows() {
+    let a = shingles(&["in", "the", "beginning"], 2);
+    let b = shingles(&["the", "beginning"], 2);
+    assert_eq!(a.len(), 2);
+    assert_eq!(a[1], b[0]);
+    assert!(shingles(&["in"], 2).is_empty());
+  }
+
+  #[test]
+  fn find_runs_finds_maximal_runs() {
+    let a = shingles(&["a", "b", "c", "d", "x", "b", "c"], 2);
+    let b = shingles(&["y", "a", "b", "c", "d", "z", "b", "c"], 2);
+    let runs = find_runs(&a, &b, false, &options(2), |_, _| true);
+
+    let found = runs.iter().map(|r| (r.a, r.b, r.len)).collect::<Vec<_>>();
+    // "a b c d" is one run, and isn't also returned from "b c" onwards.
+    assert_eq!(found, vec![(0, 1, 4), (1, 6, 2), (5, 2, 2), (5, 6, 2)]);
+
+    let runs = find_runs(&a, &b, false, &options(3), |_, _| true);
+    assert_eq!(runs.len(), 1);
+  }
+
+  #[test]
+  fn find_runs_starts_with_common_shingles() {
+    // "and it" is too common to start a run, but the passage that it opens is found whole.
+    let a = shingles(&["and", "it", "came", "to"], 2);
+    let b = shingles(&["and", "it", "was", "and", "it", "came", "to"], 2);
+    let options = RunOptions {
+      size: 2,
+      max_occurrences: 1,
+      min_len: 3,
+    };
+    let runs = find_runs(&a, &b, false, &options, |_, _| true);
+    let found = runs.iter().map(|r| (r.a, r.b, r.len)).collect::<Vec<_>>();
+    assert_eq!(found, vec![(0, 3, 4)]);
+  }
+
+  #[test]
+  fn find_runs_within_a_text_returns_each_pair_once() {
+    let tokens = shingles(&["a", "b", "c", "x", "a", "b", "c"], 2);
+    let runs = find_runs(&tokens, &tokens, true, &options(3), |_, _| true);
+    let found = runs.iter().map(|r| (r.a, r.b, r.len)).collect::<Vec<_>>();
+    assert_eq!(found, vec![(0, 4, 3)]);
+
+    let runs = find_runs(&tokens, &tokens, true, &options(3), |i, _| i > 0);
+    assert!(runs.is_empty());
+  }
+}
@@ -12,14 +12,34 @@ pub trait Stats {
     value: &str,
   );
 
+  /// Start a named range, such as a pericope, at a location. The range continues through
+  /// the values added after it, until the next range starts or the l0 level ends.
+  fn add_range(self: &mut Self, l0: usize, l1: usize, l2: Option<usize>, name: &str);
+
+  /// Count the length of text at a location that is words of Christ.
+  fn add_red_letter(self: &mut Self, l0: usize, l1: usize, len: usize);
+
   fn describe(self: &Self, l0: Option<usize>, l1: Option<usize>, l2: Option<usize>) -> String;
 }
 
+#[derive(Default, Deserialize, Serialize)]
+pub struct NamedRange {
+  pub name: String,
+  pub start: (usize, Option<usize>),
+  pub end: (usize, Option<usize>),
+}
+
 #[derive(Default, Deserialize, Serialize)]
 pub struct LeafStats {
   #[serde(skip_serializing_if = "String::is_empty", default)]
   pub name: String,
   pub len: usize,
+  #[serde(skip_serializing_if = "is_zero", default)]
+  pub red_letter_len: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+  *n == 0
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -27,6 +47,8 @@ pub struct NodeStats<T> {
   #[serde(skip_serializing_if = "String::is_empty", default)]
   pub name: String,
   pub children: Vec<T>,
+  #[serde(skip_serializing_if = "Vec::is_empty", default)]
+  pub ranges: Vec<NamedRange>,
 }
 
 impl<T> NodeStats<T>
@@ -50,6 +72,7 @@ impl L0L1Stats {
     L0L1Stats {
       name,
       children: Vec::new(),
+      ranges: Vec::new(),
     }
   }
 }
@@ -61,7 +84,7 @@ impl Stats for L0L1Stats {
     l0_name: Option<&str>,
     l1: usize,
     l1_name: Option<&str>,
-    _l2: Option<usize>,
+    l2: Option<usize>,
     value: &str,
   ) {
     let mut l0_stats = self.add_level(l0);
@@ -71,6 +94,10 @@ impl Stats for L0L1Stats {
       }
     }
 
+    if let Some(range) = l0_stats.ranges.last_mut() {
+      range.end = (l1, l2);
+    }
+
     let mut l1_stats = l0_stats.add_level(l1);
     if l1_stats.name.len() == 0 {
       if let Some(n) = l1_name {
@@ -81,6 +108,18 @@ impl Stats for L0L1Stats {
     l1_stats.len += value.len() + 1;
   }
 
+  fn add_range(self: &mut Self, l0: usize, l1: usize, l2: Option<usize>, name: &str) {
+    self.add_level(l0).ranges.push(NamedRange {
+      name: String::from(name),
+      start: (l1, l2),
+      end: (l1, l2),
+    });
+  }
+
+  fn add_red_letter(self: &mut Self, l0: usize, l1: usize, len: usize) {
+    self.add_level(l0).add_level(l1).red_letter_len += len;
+  }
+
   fn describe(self: &Self, l0: Option<usize>, l1: Option<usize>, l2: Option<usize>) -> String {
     let l0_child = l0.and_then(|loc| self.children.get(loc));
     let l1_child = l0_child
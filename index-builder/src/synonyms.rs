@@ -0,0 +1,172 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Groups of interchangeable words, mostly names that are spelled differently across
+/// translations and testaments. Loaded from `synonyms.json` in the data directory, which
+/// contains a list of groups, e.g. `{ "groups": [["elijah", "elias"]] }`.
+#[derive(Default, Deserialize)]
+pub struct Synonyms {
+  groups: Vec<Vec<String>>,
+  #[serde(skip)]
+  lookup: HashMap<String, usize>,
+}
+
+/// Characters that the query grammar treats as syntax rather than as part of a word.
+const QUERY_SYNTAX_CHARS: &[char] = &[':', '^', '{', '}', '"', '[', ']', '(', ')'];
+
+impl Synonyms {
+  pub fn load(dir: &Path) -> Result<Synonyms> {
+    let path = dir.join("synonyms.json");
+    let mut synonyms: Synonyms = match File::open(path) {
+      Ok(f) => serde_json::from_reader(f)?,
+      Err(_) => return Ok(Synonyms::default()),
+    };
+
+    for (i, group) in synonyms.groups.iter_mut().enumerate() {
+      for word in group.iter_mut() {
+        *word = word.to_lowercase();
+        synonyms.lookup.insert(word.clone(), i);
+      }
+    }
+
+    Ok(synonyms)
+  }
+
+  /// Rewrite a query so that each bare word with synonyms becomes a disjunction of the
+  /// group, e.g. `elijah` becomes `(elijah OR elias)`. Phrases, field-qualified terms,
+  /// ranges, and operators are left alone.
+  pub fn expand(&self, query: &str) -> String {
+    if self.lookup.is_empty() {
+      return String::from(query);
+    }
+
+    let mut output = String::with_capacity(query.len());
+    let mut chars = query.char_indices().peekable();
+    let mut in_field_value = false;
+
+    while let Some((start, c)) = chars.next() {
+      match c {
+        '"' => {
+          // Copy phrases through unchanged.
+          output.push(c);
+          for (_, c) in &mut chars {
+            output.push(c);
+            if c == '"' {
+              break;
+            }
+          }
+          in_field_value = false;
+        }
+        '[' | '{' => {
+          // Copy ranges through unchanged.
+          output.push(c);
+          for (_, c) in &mut chars {
+            output.push(c);
+            if c == ']' || c == '}' {
+              break;
+            }
+          }
+          in_field_value = false;
+        }
+        c if c.is_whitespace() || QUERY_SYNTAX_CHARS.contains(&c) => {
+          output.push(c);
+          in_field_value = false;
+        }
+        '-' | '+' if !in_field_value => output.push(c),
+        _ => {
+          let mut end = start + c.len_utf8();
+          while let Some(&(i, next)) = chars.peek() {
+            if next.is_whitespace() || QUERY_SYNTAX_CHARS.contains(&next) {
+              break;
+            }
+            end = i + next.len_utf8();
+            chars.next();
+          }
+
+          let word = &query[start..end];
+          let is_field_name = matches!(chars.peek(), Some((_, ':')));
+          let group = if in_field_value || is_field_name {
+            None
+          } else {
+            self
+              .lookup
+              .get(&word.to_lowercase())
+              .map(|&i| &self.groups[i])
+          };
+
+          match group {
+            Some(group) => {
+              output.push('(');
+              output.push_str(&group.join(" OR "));
+              output.push(')');
+            }
+            None => output.push_str(word),
+          }
+
+          in_field_value = is_field_name;
+          if is_field_name {
+            // Consume the colon here so that the value that follows is left alone.
+            chars.next();
+            output.push(':');
+          }
+        }
+      }
+    }
+
+    output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn synonyms() -> Synonyms {
+    let groups = vec![
+      vec![String::from("elijah"), String::from("elias")],
+      vec![String::from("joshua"), String::from("jesus")],
+    ];
+    let mut lookup = HashMap::new();
+    for (i, group) in groups.iter().enumerate() {
+      for word in group {
+        lookup.insert(word.clone(), i);
+      }
+    }
+    Synonyms { groups, lookup }
+  }
+
+  #[test]
+  fn expands_bare_words() {
+    let synonyms = synonyms();
+    assert_eq!(synonyms.expand("elijah"), "(elijah OR elias)");
+    assert_eq!(
+      synonyms.expand("Elias prophet"),
+      "(elijah OR elias) prophet"
+    );
+    assert_eq!(
+      synonyms.expand("+elijah -jesus"),
+      "+(elijah OR elias) -(joshua OR jesus)"
+    );
+    assert_eq!(synonyms.expand("(elijah)"), "((elijah OR elias))");
+  }
+
+  #[test]
+  fn leaves_other_syntax_alone() {
+    let synonyms = synonyms();
+    assert_eq!(
+      synonyms.expand("\"elijah the prophet\""),
+      "\"elijah the prophet\""
+    );
+    assert_eq!(synonyms.expand("text:elijah"), "text:elijah");
+    assert_eq!(synonyms.expand("elijah:x"), "elijah:x");
+    assert_eq!(
+      synonyms.expand("l0:[elijah TO jesus]"),
+      "l0:[elijah TO jesus]"
+    );
+    assert_eq!(synonyms.expand("moses"), "moses");
+    assert_eq!(Synonyms::default().expand("elijah"), "elijah");
+  }
+}
@@ -0,0 +1,345 @@
+use std::borrow::Cow;
+use tantivy::tokenizer::{
+  BoxTokenStream, PreTokenizedStream, PreTokenizedString, Token, TokenFilter, TokenStream,
+  Tokenizer,
+};
+
+fn is_cjk(c: char) -> bool {
+  matches!(c,
+    '\u{1100}'..='\u{11ff}' // Hangul Jamo
+    | '\u{3040}'..='\u{309f}' // Hiragana
+    | '\u{30a0}'..='\u{30ff}' // Katakana
+    | '\u{3130}'..='\u{318f}' // Hangul Compatibility Jamo
+    | '\u{3400}'..='\u{4dbf}' // CJK Unified Ideographs Extension A
+    | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+    | '\u{ac00}'..='\u{d7af}' // Hangul Syllables
+    | '\u{f900}'..='\u{faff}' // CJK Compatibility Ideographs
+    | '\u{20000}'..='\u{2a6df}' // CJK Unified Ideographs Extension B
+  )
+}
+
+/// Tokenizes Chinese, Japanese, and Korean text, which doesn't separate words with spaces.
+///
+/// Runs of CJK characters become overlapping bigrams, in the style of Lucene's CJKAnalyzer.
+/// Each character is also emitted as a unigram at the same position as the bigram it starts,
+/// so that single-character queries still match. Any other alphanumeric text is split into
+/// words the same way as `SimpleTokenizer`.
+#[derive(Clone)]
+pub struct CjkTokenizer;
+
+impl Tokenizer for CjkTokenizer {
+  fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut add_token = |offset_from: usize, offset_to: usize, position: usize| {
+      tokens.push(Token {
+        offset_from,
+        offset_to,
+        position,
+        text: String::from(&text[offset_from..offset_to]),
+        position_length: 1,
+      });
+    };
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+      if is_cjk(c) {
+        add_token(offset, offset + c.len_utf8(), position);
+        if let Some(&(next_offset, next_c)) = chars.peek() {
+          if is_cjk(next_c) {
+            add_token(offset, next_offset + next_c.len_utf8(), position);
+          }
+        }
+        position += 1;
+      } else if c.is_alphanumeric() {
+        let mut end = offset + c.len_utf8();
+        while let Some(&(next_offset, next_c)) = chars.peek() {
+          if !next_c.is_alphanumeric() || is_cjk(next_c) {
+            break;
+          }
+
+          end = next_offset + next_c.len_utf8();
+          chars.next();
+        }
+
+        add_token(offset, end, position);
+        position += 1;
+      }
+    }
+
+    PreTokenizedStream::from(PreTokenizedString {
+      text: String::from(text),
+      tokens,
+    })
+    .into()
+  }
+}
+
+/// Rewrites Early Modern English forms, as found in the KJV and its contemporaries, to
+/// their modern equivalents so that modern-English queries match them. This must run after
+/// `LowerCaser` and before stopword removal and stemming.
+///
+/// Verb endings are rewritten to the modern third person "-es" form, which the stemmer then
+/// reduces the same way it does the modern word: "loveth" and "lovest" become "loves".
+#[derive(Clone)]
+pub struct ArchaicNormalizer;
+
+impl TokenFilter for ArchaicNormalizer {
+  fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+    BoxTokenStream::from(NormalizerTokenStream {
+      tail: token_stream,
+      normalize: modernize,
+    })
+  }
+}
+
+/// Normalizes Strong's numbers, so that "g26", "G26", and "G0026" are all indexed as "G26".
+#[derive(Clone)]
+pub struct StrongNormalizer;
+
+impl TokenFilter for StrongNormalizer {
+  fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+    BoxTokenStream::from(NormalizerTokenStream {
+      tail: token_stream,
+      normalize: |word| normalize_strong(word).map(Cow::Owned),
+    })
+  }
+}
+
+/// Replaces the text of each token that the normalize function returns a value for.
+pub struct NormalizerTokenStream<'a> {
+  tail: BoxTokenStream<'a>,
+  normalize: fn(&str) -> Option<Cow<'static, str>>,
+}
+
+impl<'a> TokenStream for NormalizerTokenStream<'a> {
+  fn advance(&mut self) -> bool {
+    if !self.tail.advance() {
+      return false;
+    }
+
+    if let Some(normalized) = (self.normalize)(&self.tail.token().text) {
+      let text = &mut self.tail.token_mut().text;
+      text.clear();
+      text.push_str(&normalized);
+    }
+
+    true
+  }
+
+  fn token(&self) -> &Token {
+    self.tail.token()
+  }
+
+  fn token_mut(&mut self) -> &mut Token {
+    self.tail.token_mut()
+  }
+}
+
+/// The modern pronouns that `ArchaicNormalizer` rewrites archaic ones to.
+pub const MODERNIZED_PRONOUNS: &[&str] = &["you", "your", "yourself"];
+
+/// Words that end in "-eth" or "-est" but aren't archaic verb forms, such as nouns, names,
+/// ordinal numbers, and superlatives.
+const ARCHAIC_SUFFIX_EXCEPTIONS: &[&str] = &[
+  "arrest",
+  "ashtoreth",
+  "attest",
+  "behest",
+  "beth",
+  "chiefest",
+  "chinnereth",
+  "choicest",
+  "conquest",
+  "contest",
+  "crest",
+  "dearest",
+  "digest",
+  "dishonest",
+  "earnest",
+  "eightieth",
+  "eldest",
+  "elisabeth",
+  "fairest",
+  "fattest",
+  "fiftieth",
+  "forest",
+  "fortieth",
+  "goodliest",
+  "greatest",
+  "guest",
+  "harvest",
+  "hazarmaveth",
+  "heth",
+  "highest",
+  "holiest",
+  "honest",
+  "infest",
+  "interest",
+  "invest",
+  "japheth",
+  "jetheth",
+  "largest",
+  "lowest",
+  "manifest",
+  "meanest",
+  "mightiest",
+  "modest",
+  "molest",
+  "nazareth",
+  "nearest",
+  "ninetieth",
+  "oldest",
+  "priest",
+  "protest",
+  "request",
+  "seth",
+  "seventieth",
+  "shibboleth",
+  "sixtieth",
+  "smallest",
+  "strongest",
+  "suggest",
+  "teeth",
+  "tempest",
+  "thirtieth",
+  "twentieth",
+  "unrest",
+  "weakest",
+  "wisest",
+  "wrest",
+  "youngest",
+];
+
+fn modernize(word: &str) -> Option<Cow<'static, str>> {
+  let replacement = match word {
+    "thee" | "thou" | "ye" => "you",
+    "thy" | "thine" => "your",
+    "thyself" => "yourself",
+    "art" => "are",
+    "wast" | "wert" => "were",
+    "hast" => "have",
+    "hath" => "has",
+    "dost" => "do",
+    "doth" => "does",
+    "didst" => "did",
+    "hadst" => "had",
+    "shalt" => "shall",
+    "wilt" => "will",
+    "canst" => "can",
+    "couldest" => "could",
+    "wouldest" => "would",
+    "shouldest" => "should",
+    "mayest" => "may",
+    "mightest" => "might",
+    "saith" => "says",
+    "seest" | "seeth" => "sees",
+    "goest" | "goeth" => "goes",
+    "doest" | "doeth" => "does",
+    "diest" | "dieth" => "dies",
+    "liest" | "lieth" => "lies",
+    "beest" => "are",
+    "spake" => "spoke",
+    "shew" => "show",
+    "shewed" => "showed",
+    "shewest" | "sheweth" => "shows",
+    "shewing" => "showing",
+    "shewn" => "shown",
+    "unto" => "to",
+    _ => {
+      if ARCHAIC_SUFFIX_EXCEPTIONS.contains(&word) {
+        return None;
+      }
+
+      // Require a few characters ahead of the suffix to avoid words like "best" and "rest".
+      return word
+        .strip_suffix("eth")
+        .or_else(|| word.strip_suffix("est"))
+        .filter(|stem| stem.chars().count() >= 3)
+        .map(|stem| Cow::Owned(format!("{}es", stem)));
+    }
+  };
+
+  Some(Cow::Borrowed(replacement))
+}
+
+/// Uppercase the language prefix of a Strong's number and remove leading zeros from the
+/// number. Returns None if the number is already in this form.
+pub fn normalize_strong(number: &str) -> Option<String> {
+  let digits_start = number
+    .find(|c: char| c.is_ascii_digit())
+    .unwrap_or(number.len());
+  let (prefix, rest) = number.split_at(digits_start);
+  let digits = rest.trim_start_matches('0');
+
+  let normalized = format!("{}{}", prefix.to_uppercase(), digits.to_uppercase());
+  if normalized == number {
+    None
+  } else {
+    Some(normalized)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn modern(word: &str) -> String {
+    modernize(word)
+      .map(|w| w.into_owned())
+      .unwrap_or_else(|| String::from(word))
+  }
+
+  #[test]
+  fn modernize_replaces_archaic_words() {
+    assert_eq!(modern("thee"), "you");
+    assert_eq!(modern("thine"), "your");
+    assert_eq!(modern("hath"), "has");
+    assert_eq!(modern("seeth"), "sees");
+    assert_eq!(modern("goest"), "goes");
+    assert_eq!(modern("doeth"), "does");
+    assert_eq!(modern("lieth"), "lies");
+    assert_eq!(modern("spake"), "spoke");
+    assert_eq!(modern("unto"), "to");
+  }
+
+  #[test]
+  fn modernize_rewrites_verb_endings() {
+    assert_eq!(modern("loveth"), "loves");
+    assert_eq!(modern("knowest"), "knowes");
+    assert_eq!(modern("pitieth"), "pities");
+  }
+
+  #[test]
+  fn modernize_keeps_other_words() {
+    for word in &[
+      "love",
+      "best",
+      "rest",
+      "priest",
+      "teeth",
+      "nazareth",
+      "greatest",
+      "eldest",
+      "twentieth",
+      "fortieth",
+    ] {
+      assert_eq!(modernize(word), None, "{}", word);
+    }
+  }
+
+  #[test]
+  fn modernized_pronouns_are_targets() {
+    for word in &["thee", "thou", "ye", "thy", "thine", "thyself"] {
+      assert!(MODERNIZED_PRONOUNS.contains(&modern(word).as_str()));
+    }
+  }
+
+  #[test]
+  fn normalize_strong_numbers() {
+    assert_eq!(normalize_strong("g26"), Some(String::from("G26")));
+    assert_eq!(normalize_strong("G0026"), Some(String::from("G26")));
+    assert_eq!(normalize_strong("h07225"), Some(String::from("H7225")));
+    assert_eq!(normalize_strong("G26"), None);
+  }
+}
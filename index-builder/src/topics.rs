@@ -0,0 +1,238 @@
+use crate::books::Location;
+use crate::corpus::Corpus;
+use crate::rng::XorShift;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Options for fitting a topic model.
+#[derive(Debug, Clone)]
+pub struct TopicOptions {
+  pub topics: usize,
+  /// Gibbs sampling sweeps over the whole text.
+  pub iterations: usize,
+  /// The prior on each chapter's topic weights. Smaller values give chapters fewer topics.
+  pub alpha: f64,
+  /// The prior on each topic's word weights. Smaller values give topics fewer words.
+  pub beta: f64,
+  /// The number of words to keep for each topic.
+  pub top_words: usize,
+  /// Words that appear in more than this fraction of chapters say little about any topic.
+  pub max_chapter_fraction: f64,
+}
+
+impl Default for TopicOptions {
+  fn default() -> Self {
+    TopicOptions {
+      topics: 20,
+      iterations: 200,
+      alpha: 0.1,
+      beta: 0.01,
+      top_words: 15,
+      max_chapter_fraction: 0.5,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TopicWord {
+  pub word: String,
+  pub weight: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Topic {
+  /// The most likely words in the topic, most likely first.
+  pub words: Vec<TopicWord>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChapterTopics {
+  #[serde(flatten)]
+  pub location: Location,
+  /// The weight of each topic in the chapter, adding up to 1.
+  pub weights: Vec<f32>,
+}
+
+/// An LDA topic model of a text's chapters, fit by the `analyze topics` command and stored
+/// as `topics-<book_id>.json` in the data directory.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Topics {
+  pub book_id: String,
+  pub topics: Vec<Topic>,
+  pub chapters: Vec<ChapterTopics>,
+}
+
+impl Topics {
+  pub fn file_name(book_id: &str) -> String {
+    format!("topics-{}.json", book_id)
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join(Topics::file_name(&self.book_id));
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+
+  /// Fit a topic model to the chapters of a text with collapsed Gibbs sampling.
+  pub fn fit(corpus: &Corpus, options: &TopicOptions) -> Result<Topics> {
+    // Gather each chapter's tokens.
+    let mut chapter_tokens: Vec<(Location, Vec<&str>)> = Vec::new();
+    for verse in &corpus.verses {
+      let location = Location {
+        l2: None,
+        ..verse.location
+      };
+      let tokens = verse.tokens.iter().map(|t| t.as_str());
+      match chapter_tokens.last_mut() {
+        Some((chapter, chapter_tokens)) if *chapter == location => chapter_tokens.extend(tokens),
+        _ => chapter_tokens.push((location, tokens.collect())),
+      }
+    }
+
+    // Keep words that appear in more than one chapter, but not in most of them.
+    let mut chapter_freqs: HashMap<&str, usize> = HashMap::new();
+    for (_, tokens) in &chapter_tokens {
+      let mut words = tokens.clone();
+      words.sort_unstable();
+      words.dedup();
+      for word in words {
+        *chapter_freqs.entry(word).or_insert(0) += 1;
+      }
+    }
+    let max_freq = (chapter_tokens.len() as f64 * options.max_chapter_fraction).max(2.0) as usize;
+    let mut vocabulary = chapter_freqs
+      .iter()
+      .filter(|(_, &freq)| freq >= 2 && freq <= max_freq)
+      .map(|(&word, _)| word)
+      .collect::<Vec<_>>();
+    vocabulary.sort_unstable();
+    if vocabulary.is_empty() || options.topics == 0 {
+      return Err(anyhow!(
+        "Not enough text in {} to find topics",
+        corpus.book_id
+      ));
+    }
+    let word_ids = vocabulary
+      .iter()
+      .enumerate()
+      .map(|(i, &word)| (word, i))
+      .collect::<HashMap<_, _>>();
+
+    let docs = chapter_tokens
+      .iter()
+      .map(|(_, tokens)| {
+        tokens
+          .iter()
+          .filter_map(|t| word_ids.get(t).copied())
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+
+    let k = options.topics;
+    let v = vocabulary.len();
+    let mut rng = XorShift::new(0x9E37_79B9_7F4A_7C15);
+    let mut doc_topic = vec![vec![0usize; k]; docs.len()];
+    let mut topic_word = vec![vec![0usize; v]; k];
+    let mut topic_total = vec![0usize; k];
+    let mut assignments = docs
+      .iter()
+      .enumerate()
+      .map(|(d, words)| {
+        words
+          .iter()
+          .map(|&w| {
+            let z = (rng.next_u64() % k as u64) as usize;
+            doc_topic[d][z] += 1;
+            topic_word[z][w] += 1;
+            topic_total[z] += 1;
+            z
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+
+    let v_beta = v as f64 * options.beta;
+    let mut probabilities = vec![0.0; k];
+    for _ in 0..options.iterations {
+      for (d, words) in docs.iter().enumerate() {
+        for (i, &w) in words.iter().enumerate() {
+          let old = assignments[d][i];
+          doc_topic[d][old] -= 1;
+          topic_word[old][w] -= 1;
+          topic_total[old] -= 1;
+
+          let mut total = 0.0;
+          for (z, p) in probabilities.iter_mut().enumerate() {
+            total += (doc_topic[d][z] as f64 + options.alpha)
+              * (topic_word[z][w] as f64 + options.beta)
+              / (topic_total[z] as f64 + v_beta);
+            *p = total;
+          }
+
+          let target = rng.next_f64() * total;
+          let new = probabilities
+            .iter()
+            .position(|&p| p > target)
+            .unwrap_or(k - 1);
+
+          assignments[d][i] = new;
+          doc_topic[d][new] += 1;
+          topic_word[new][w] += 1;
+          topic_total[new] += 1;
+        }
+      }
+    }
+
+    let surface_forms = corpus.surface_forms();
+    let topics = topic_word
+      .iter()
+      .zip(&topic_total)
+      .map(|(counts, &total)| {
+        let mut words = counts
+          .iter()
+          .enumerate()
+          .filter(|(_, &count)| count > 0)
+          .map(|(w, &count)| TopicWord {
+            word: surface_forms
+              .get(vocabulary[w])
+              .cloned()
+              .unwrap_or_else(|| String::from(vocabulary[w])),
+            weight: ((count as f64 + options.beta) / (total as f64 + v_beta)) as f32,
+          })
+          .collect::<Vec<_>>();
+        words.sort_by(|a, b| {
+          b.weight
+            .partial_cmp(&a.weight)
+            .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        words.truncate(options.top_words);
+        Topic { words }
+      })
+      .collect();
+
+    let k_alpha = k as f64 * options.alpha;
+    let chapters = chapter_tokens
+      .iter()
+      .zip(&doc_topic)
+      .zip(&docs)
+      .map(|(((location, _), counts), words)| ChapterTopics {
+        location: *location,
+        weights: counts
+          .iter()
+          .map(|&c| ((c as f64 + options.alpha) / (words.len() as f64 + k_alpha)) as f32)
+          .collect(),
+      })
+      .collect();
+
+    Ok(Topics {
+      book_id: corpus.book_id.clone(),
+      topics,
+      chapters,
+    })
+  }
+}
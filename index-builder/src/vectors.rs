@@ -0,0 +1,331 @@
+use crate::books::Location;
+use crate::corpus::Corpus;
+use crate::error::Error;
+use crate::rng::XorShift;
+use anyhow::{anyhow, Result};
+use nalgebra::DMatrix;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Options for building a text's vectors.
+#[derive(Debug, Clone)]
+pub struct VectorOptions {
+  /// The number of dimensions in each vector.
+  pub dimensions: usize,
+  /// Terms that appear in fewer verses than this are left out.
+  pub min_doc_freq: usize,
+  /// Extra dimensions sampled by the randomized SVD, which improve its accuracy.
+  pub oversample: usize,
+  /// Power iterations in the randomized SVD, which help when the singular values fall off
+  /// slowly, as they do for text.
+  pub power_iterations: usize,
+}
+
+impl Default for VectorOptions {
+  fn default() -> Self {
+    VectorOptions {
+      dimensions: 100,
+      min_doc_freq: 2,
+      oversample: 10,
+      power_iterations: 2,
+    }
+  }
+}
+
+/// A row of a sparse matrix, as (column, value) pairs.
+type SparseRow = Vec<(usize, f32)>;
+
+/// A vector for each verse and chapter of a text, from latent semantic analysis: a
+/// TF-IDF matrix of the verses is reduced with a truncated SVD, so that verses about the
+/// same things are close together even when they share no words. Each vector has unit
+/// length, so the dot product of two vectors is their cosine similarity.
+///
+/// The vectors for a text are stored in the data directory as `vectors-<book_id>.json`,
+/// which holds the locations, and `vectors-<book_id>.bin`, which holds the verse vectors
+/// and then the chapter vectors as little-endian f32s.
+#[derive(Serialize, Deserialize)]
+pub struct VectorSet {
+  pub book_id: String,
+  pub dimensions: usize,
+  pub verses: Vec<Location>,
+  pub chapters: Vec<Location>,
+  #[serde(skip)]
+  verse_vectors: Vec<f32>,
+  #[serde(skip)]
+  chapter_vectors: Vec<f32>,
+}
+
+/// A nearby verse or chapter, and its cosine similarity.
+#[derive(Serialize, Debug)]
+pub struct Neighbor {
+  #[serde(flatten)]
+  pub location: Location,
+  pub similarity: f32,
+}
+
+/// Multiply a sparse matrix by a dense one.
+fn sparse_mul(rows: &[SparseRow], m: &DMatrix<f32>) -> DMatrix<f32> {
+  let mut result = DMatrix::zeros(rows.len(), m.ncols());
+  for (i, row) in rows.iter().enumerate() {
+    for &(j, value) in row {
+      for k in 0..m.ncols() {
+        result[(i, k)] += value * m[(j, k)];
+      }
+    }
+  }
+  result
+}
+
+/// Multiply the transpose of a sparse matrix with `num_columns` columns by a dense matrix.
+fn sparse_transpose_mul(rows: &[SparseRow], num_columns: usize, m: &DMatrix<f32>) -> DMatrix<f32> {
+  let mut result = DMatrix::zeros(num_columns, m.ncols());
+  for (i, row) in rows.iter().enumerate() {
+    for &(j, value) in row {
+      for k in 0..m.ncols() {
+        result[(j, k)] += value * m[(i, k)];
+      }
+    }
+  }
+  result
+}
+
+fn normalize(v: &mut [f32]) {
+  let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+  if norm > 0.0 {
+    v.iter_mut().for_each(|x| *x /= norm);
+  }
+}
+
+impl VectorSet {
+  /// Build the vectors for a text that has been indexed.
+  pub fn build(
+    index: &tantivy::Index,
+    book_id: &str,
+    options: &VectorOptions,
+  ) -> Result<VectorSet, Error> {
+    let corpus = Corpus::load(index, book_id, None)?;
+
+    // Count the verses that each term appears in, and keep the terms that are common enough
+    // to relate verses to each other.
+    let mut doc_freqs: HashMap<&str, usize> = HashMap::new();
+    for verse in &corpus.verses {
+      let mut terms = verse.tokens.iter().map(|t| t.as_str()).collect::<Vec<_>>();
+      terms.sort_unstable();
+      terms.dedup();
+      for term in terms {
+        *doc_freqs.entry(term).or_insert(0) += 1;
+      }
+    }
+
+    let mut vocabulary = doc_freqs
+      .iter()
+      .filter(|(_, &df)| df >= options.min_doc_freq)
+      .map(|(&term, _)| term)
+      .collect::<Vec<_>>();
+    vocabulary.sort_unstable();
+    if vocabulary.is_empty() {
+      return Err(Error::Other(anyhow!(
+        "{} has no terms that appear in at least {} verses",
+        book_id,
+        options.min_doc_freq
+      )));
+    }
+
+    let term_ids = vocabulary
+      .iter()
+      .enumerate()
+      .map(|(i, &term)| (term, i))
+      .collect::<HashMap<_, _>>();
+
+    let num_verses = corpus.verses.len() as f32;
+    let idf = vocabulary
+      .iter()
+      .map(|term| (num_verses / doc_freqs[term] as f32).ln())
+      .collect::<Vec<_>>();
+
+    // Sublinear TF-IDF, normalized to unit length.
+    let tf_idf = |tokens: &mut dyn Iterator<Item = &String>| -> SparseRow {
+      let mut counts: HashMap<usize, usize> = HashMap::new();
+      for id in tokens.filter_map(|t| term_ids.get(t.as_str())) {
+        *counts.entry(*id).or_insert(0) += 1;
+      }
+
+      let mut row = counts
+        .into_iter()
+        .map(|(id, tf)| (id, (1.0 + (tf as f32).ln()) * idf[id]))
+        .collect::<Vec<_>>();
+      let norm = row.iter().map(|(_, x)| x * x).sum::<f32>().sqrt();
+      if norm > 0.0 {
+        row.iter_mut().for_each(|(_, x)| *x /= norm);
+      }
+      row
+    };
+
+    let verse_rows = corpus
+      .verses
+      .iter()
+      .map(|verse| tf_idf(&mut verse.tokens.iter()))
+      .collect::<Vec<_>>();
+
+    let mut chapters: Vec<(Location, Vec<usize>)> = Vec::new();
+    for (i, verse) in corpus.verses.iter().enumerate() {
+      let location = Location {
+        l2: None,
+        ..verse.location
+      };
+      match chapters.last_mut() {
+        Some((chapter, verses)) if *chapter == location => verses.push(i),
+        _ => chapters.push((location, vec![i])),
+      }
+    }
+    let chapter_rows = chapters
+      .iter()
+      .map(|(_, verses)| tf_idf(&mut verses.iter().flat_map(|&v| corpus.verses[v].tokens.iter())))
+      .collect::<Vec<_>>();
+
+    // Randomized SVD, after Halko, Martinsson, and Tropp. The verse matrix is projected onto
+    // a random subspace, which is refined with power iterations, and the small matrix that
+    // results is decomposed exactly.
+    let dimensions = options
+      .dimensions
+      .min(vocabulary.len())
+      .min(verse_rows.len());
+    let samples = (dimensions + options.oversample)
+      .min(vocabulary.len())
+      .min(verse_rows.len());
+    let mut rng = XorShift::new(0x2545_F491_4F6C_DD1D);
+    let omega = DMatrix::from_fn(vocabulary.len(), samples, |_, _| rng.next_normal());
+
+    let mut q = sparse_mul(&verse_rows, &omega).qr().q();
+    for _ in 0..options.power_iterations {
+      let z = sparse_transpose_mul(&verse_rows, vocabulary.len(), &q)
+        .qr()
+        .q();
+      q = sparse_mul(&verse_rows, &z).qr().q();
+    }
+
+    // B = Q^T A, which is small enough to decompose directly.
+    let b = sparse_transpose_mul(&verse_rows, vocabulary.len(), &q).transpose();
+    let svd = b.svd(false, true);
+    let singular_values = svd.singular_values;
+    let v_t = svd
+      .v_t
+      .ok_or_else(|| Error::Other(anyhow!("Failed to decompose the term matrix")))?;
+
+    // The SVD doesn't sort its singular values, so take the rows for the largest ones.
+    let mut order = (0..singular_values.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+      singular_values[b]
+        .partial_cmp(&singular_values[a])
+        .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order.truncate(dimensions);
+
+    // Fold each row into the reduced space by projecting it onto the top right singular
+    // vectors.
+    let project = |rows: &[SparseRow]| {
+      let mut vectors = Vec::with_capacity(rows.len() * dimensions);
+      for row in rows {
+        let mut v = order
+          .iter()
+          .map(|&d| row.iter().map(|&(j, x)| x * v_t[(d, j)]).sum::<f32>())
+          .collect::<Vec<_>>();
+        normalize(&mut v);
+        vectors.extend(v);
+      }
+      vectors
+    };
+
+    Ok(VectorSet {
+      book_id: String::from(book_id),
+      dimensions,
+      verses: corpus.verses.iter().map(|v| v.location).collect(),
+      chapters: chapters.iter().map(|(location, _)| *location).collect(),
+      verse_vectors: project(&verse_rows),
+      chapter_vectors: project(&chapter_rows),
+    })
+  }
+
+  fn base_name(book_id: &str) -> String {
+    format!("vectors-{}", book_id)
+  }
+
+  /// The name of the metadata file in the data directory.
+  pub fn file_name(book_id: &str) -> String {
+    format!("{}.json", VectorSet::base_name(book_id))
+  }
+
+  /// Load a text's vectors, from the path of its metadata file.
+  pub fn load(path: &Path) -> Result<VectorSet> {
+    let f = File::open(path)?;
+    let mut vectors: VectorSet = serde_json::from_reader(f)?;
+
+    let mut data = Vec::new();
+    BufReader::new(File::open(path.with_extension("bin"))?).read_to_end(&mut data)?;
+    let mut values = data
+      .chunks_exact(4)
+      .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    let verse_len = vectors.verses.len() * vectors.dimensions;
+    let chapter_len = vectors.chapters.len() * vectors.dimensions;
+    vectors.verse_vectors = values.by_ref().take(verse_len).collect();
+    vectors.chapter_vectors = values.take(chapter_len).collect();
+    if vectors.verse_vectors.len() != verse_len || vectors.chapter_vectors.len() != chapter_len {
+      return Err(anyhow!("Vector data for {} is truncated", vectors.book_id));
+    }
+
+    Ok(vectors)
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let base_path = dir.join(VectorSet::base_name(&self.book_id));
+
+    let f = File::create(base_path.with_extension("json"))?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+
+    let mut w = BufWriter::new(File::create(base_path.with_extension("bin"))?);
+    for x in self.verse_vectors.iter().chain(self.chapter_vectors.iter()) {
+      w.write_all(&x.to_le_bytes())?;
+    }
+    w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+    Ok(())
+  }
+
+  /// The verses nearest to a verse, or the chapters nearest to a chapter when `location`
+  /// has no l2, most similar first.
+  pub fn nearest(&self, location: Location, limit: usize) -> Option<Vec<Neighbor>> {
+    let (locations, vectors) = match location.l2 {
+      Some(_) => (&self.verses, &self.verse_vectors),
+      None => (&self.chapters, &self.chapter_vectors),
+    };
+
+    let target = locations.iter().position(|l| *l == location)?;
+    let vector = |i: usize| &vectors[i * self.dimensions..(i + 1) * self.dimensions];
+    let target_vector = vector(target);
+
+    let mut neighbors = (0..locations.len())
+      .filter(|&i| i != target)
+      .map(|i| Neighbor {
+        location: locations[i],
+        similarity: vector(i)
+          .iter()
+          .zip(target_vector)
+          .map(|(a, b)| a * b)
+          .sum(),
+      })
+      .collect::<Vec<_>>();
+    neighbors.sort_by(|a, b| {
+      b.similarity
+        .partial_cmp(&a.similarity)
+        .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    neighbors.truncate(limit);
+
+    Some(neighbors)
+  }
+}
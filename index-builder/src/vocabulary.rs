@@ -0,0 +1,252 @@
+use crate::corpus::Corpus;
+use crate::index::Analyzer;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::path::Path;
+use tantivy::Index;
+
+/// Vocabulary statistics for a text, or for one of its books. Words are counted as they
+/// are written, lowercased, with stop words and without stemming. Texts tagged with lemmas
+/// are counted by lemma instead, so that each inflection of a word doesn't count as new.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VocabularyStats {
+  /// The number of words.
+  pub tokens: usize,
+  /// The number of distinct words.
+  pub types: usize,
+  pub type_token_ratio: f64,
+  /// The words that appear only once, in alphabetical order.
+  pub hapax_legomena: Vec<String>,
+}
+
+impl VocabularyStats {
+  fn new(freqs: &[(&str, usize)]) -> Self {
+    let tokens = freqs.iter().map(|(_, freq)| freq).sum::<usize>();
+    let mut hapax_legomena = freqs
+      .iter()
+      .filter(|(_, freq)| *freq == 1)
+      .map(|(word, _)| String::from(*word))
+      .collect::<Vec<_>>();
+    hapax_legomena.sort_unstable();
+
+    VocabularyStats {
+      tokens,
+      types: freqs.len(),
+      type_token_ratio: if tokens > 0 {
+        freqs.len() as f64 / tokens as f64
+      } else {
+        0.0
+      },
+      hapax_legomena,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookVocabulary {
+  pub l0: usize,
+  /// The book's name in the text.
+  pub name: String,
+  #[serde(flatten)]
+  pub stats: VocabularyStats,
+  /// The words that appear in this book and no other, in alphabetical order.
+  pub unique: Vec<String>,
+}
+
+/// The vocabulary statistics of a text and each of its books, written by the indexer as
+/// `vocabulary-<book_id>.json` next to the text's stats file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Vocabulary {
+  pub book_id: String,
+  #[serde(flatten)]
+  pub stats: VocabularyStats,
+  pub books: Vec<BookVocabulary>,
+}
+
+impl Vocabulary {
+  pub fn file_name(book_id: &str) -> String {
+    format!("vocabulary-{}.json", book_id)
+  }
+
+  pub fn write(&self, dir: &Path) -> Result<()> {
+    let path = dir.join(Vocabulary::file_name(&self.book_id));
+    let f = File::create(path)?;
+    serde_json::to_writer(&f, self)?;
+    f.sync_all()?;
+    Ok(())
+  }
+
+  /// Read the words of a text from the index and count its vocabulary. `book_names` are
+  /// the names of the text's books, by l0.
+  pub fn build(index: &Index, book_id: &str, book_names: &[String]) -> Result<Vocabulary> {
+    let corpus = Corpus::load(index, book_id, None)?;
+
+    let words = if corpus.verses.iter().any(|v| !v.lemmas.is_empty()) {
+      corpus
+        .verses
+        .iter()
+        .flat_map(|v| {
+          v.lemmas
+            .iter()
+            .map(move |lemma| (v.location.l0, lemma.clone()))
+        })
+        .collect::<Vec<_>>()
+    } else if corpus.analyzer == Analyzer::Cjk {
+      // The CJK analyzer neither stems nor leaves out stop words, but its bigrams aren't
+      // words, so only the first token at each position is counted.
+      corpus
+        .verses
+        .iter()
+        .flat_map(|v| {
+          unigrams(&v.tokens, &v.positions).map(move |token| (v.location.l0, token.clone()))
+        })
+        .collect()
+    } else {
+      let tokenizer = index
+        .tokenizers()
+        .get("word_tokenizer")
+        .ok_or_else(|| anyhow!("Missing word tokenizer"))?;
+      let mut words = Vec::new();
+      for verse in &corpus.verses {
+        tokenizer.token_stream(&verse.text).process(&mut |token| {
+          words.push((verse.location.l0, token.text.clone()));
+        });
+      }
+      words
+    };
+
+    Ok(Vocabulary::new(book_id, &words, book_names))
+  }
+
+  /// Count the vocabulary of a text from each of its words and the l0 of the book that it
+  /// is in.
+  pub fn new(book_id: &str, words: &[(usize, String)], book_names: &[String]) -> Vocabulary {
+    let mut word_freqs: HashMap<&str, BTreeMap<usize, usize>> = HashMap::new();
+    for (l0, word) in words {
+      *word_freqs
+        .entry(word.as_str())
+        .or_default()
+        .entry(*l0)
+        .or_insert(0) += 1;
+    }
+
+    let mut text_freqs = Vec::with_capacity(word_freqs.len());
+    let mut book_freqs: BTreeMap<usize, Vec<(&str, usize)>> = BTreeMap::new();
+    let mut unique: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (word, freqs) in word_freqs {
+      text_freqs.push((word, freqs.values().sum()));
+      for (&l0, &freq) in &freqs {
+        book_freqs.entry(l0).or_default().push((word, freq));
+      }
+      if freqs.len() == 1 {
+        let l0 = *freqs.keys().next().unwrap();
+        unique.entry(l0).or_default().push(String::from(word));
+      }
+    }
+
+    // A text with only one book has no words unique to it.
+    let multiple_books = book_freqs.len() > 1;
+    let books = book_freqs
+      .into_iter()
+      .map(|(l0, freqs)| {
+        let mut unique = match unique.remove(&l0) {
+          Some(unique) if multiple_books => unique,
+          _ => Vec::new(),
+        };
+        unique.sort_unstable();
+
+        BookVocabulary {
+          l0,
+          name: book_names.get(l0).cloned().unwrap_or_default(),
+          stats: VocabularyStats::new(&freqs),
+          unique,
+        }
+      })
+      .collect();
+
+    Vocabulary {
+      book_id: String::from(book_id),
+      stats: VocabularyStats::new(&text_freqs),
+      books,
+    }
+  }
+}
+
+/// The tokens from `CjkTokenizer` without its bigrams, which share a position with the
+/// character that starts them.
+fn unigrams<'a>(tokens: &'a [String], positions: &'a [u32]) -> impl Iterator<Item = &'a String> {
+  tokens
+    .iter()
+    .zip(positions)
+    .enumerate()
+    .filter(move |&(i, (_, position))| i == 0 || positions[i - 1] != *position)
+    .map(|(_, (token, _))| token)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::tokenizers::CjkTokenizer;
+  use tantivy::tokenizer::Tokenizer;
+
+  #[test]
+  fn unigrams_skip_cjk_bigrams() {
+    let mut tokens = Vec::new();
+    let mut positions = Vec::new();
+    CjkTokenizer
+      .token_stream("神爱世人 God")
+      .process(&mut |token| {
+        tokens.push(token.text.clone());
+        positions.push(token.position as u32);
+      });
+    assert_eq!(tokens.len(), 8);
+
+    let words = unigrams(&tokens, &positions).collect::<Vec<_>>();
+    assert_eq!(words, vec!["神", "爱", "世", "人", "God"]);
+  }
+
+  fn words(words: &[(usize, &str)]) -> Vec<(usize, String)> {
+    words.iter().map(|&(l0, w)| (l0, String::from(w))).collect()
+  }
+
+  #[test]
+  fn counts_words_per_book() {
+    let names = vec![String::from("Genesis"), String::from("Exodus")];
+    let words = words(&[
+      (0, "in"),
+      (0, "the"),
+      (0, "beginning"),
+      (0, "the"),
+      (1, "the"),
+      (1, "names"),
+    ]);
+    let vocabulary = Vocabulary::new("test", &words, &names);
+
+    assert_eq!(vocabulary.stats.tokens, 6);
+    assert_eq!(vocabulary.stats.types, 4);
+    assert_eq!(
+      vocabulary.stats.hapax_legomena,
+      vec!["beginning", "in", "names"]
+    );
+
+    let genesis = &vocabulary.books[0];
+    assert_eq!(genesis.name, "Genesis");
+    assert_eq!(genesis.stats.tokens, 4);
+    assert_eq!(genesis.stats.types, 3);
+    assert_eq!(genesis.stats.hapax_legomena, vec!["beginning", "in"]);
+    assert_eq!(genesis.unique, vec!["beginning", "in"]);
+
+    let exodus = &vocabulary.books[1];
+    assert_eq!(exodus.name, "Exodus");
+    assert_eq!(exodus.unique, vec!["names"]);
+  }
+
+  #[test]
+  fn single_book_has_no_unique_words() {
+    let vocabulary = Vocabulary::new("test", &words(&[(0, "a"), (0, "b")]), &[]);
+    assert!(vocabulary.books[0].unique.is_empty());
+    assert_eq!(vocabulary.books[0].name, "");
+  }
+}